@@ -0,0 +1,431 @@
+//! AMS tray matching, mapping sliced filaments to loaded trays for [crate::job::PrintJob::ams_mapping]
+
+use crate::{
+    job::{EXTERNAL_TRAY, UNMAPPED_TRAY},
+    threemf::Filament,
+    types::{PrintValue, TrayInfo},
+};
+
+/// Maximum RGB distance for colours to be considered a match
+pub const COLOR_TOLERANCE: f32 = 48.0;
+
+/// Loaded AMS tray or external spool
+#[derive(Clone, PartialEq, Debug)]
+pub struct LoadedTray {
+    /// Tray index as used in `ams_mapping` (`ams * 4 + tray`, or [EXTERNAL_TRAY])
+    pub index: i32,
+    /// Tray information
+    pub info: TrayInfo,
+}
+
+impl LoadedTray {
+    /// Estimated remaining filament (g), if known
+    pub fn remaining(&self) -> Option<f32> {
+        let weight = self.info.tray_weight.parse::<f32>().ok()?;
+
+        match self.info.remain {
+            r if r < 0 => None,
+            r => Some(weight * r as f32 / 100.0),
+        }
+    }
+}
+
+/// Problem with a filament to tray match
+#[derive(Clone, PartialEq, Debug, displaydoc::Display)]
+pub enum MatchIssue {
+    /// no loaded tray with {0} filament
+    NoTray(String),
+    /// tray has {found} filament, expected {expected}
+    TypeMismatch { expected: String, found: String },
+    /// tray colour {found} differs from sliced colour {expected}
+    ColorMismatch { expected: String, found: String },
+    /// tray has ~{available:.0}g remaining, {required:.0}g required
+    LowFilament { required: f32, available: f32 },
+    /// tray remaining filament unknown
+    UnknownRemaining,
+}
+
+impl MatchIssue {
+    /// Issue prevents printing with the proposed mapping
+    pub fn is_blocking(&self) -> bool {
+        matches!(
+            self,
+            Self::NoTray(_) | Self::TypeMismatch { .. } | Self::LowFilament { .. }
+        )
+    }
+}
+
+/// Proposed tray for a sliced filament
+#[derive(Clone, PartialEq, Debug)]
+pub struct FilamentMatch {
+    /// Sliced filament
+    pub filament: Filament,
+    /// Matched tray index, if any
+    pub tray: Option<i32>,
+    /// Issues with the match
+    pub issues: Vec<MatchIssue>,
+}
+
+/// Proposed mapping of sliced filaments to trays
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct AmsMapping {
+    pub matches: Vec<FilamentMatch>,
+}
+
+impl AmsMapping {
+    /// Build the `ams_mapping` array, indexed by slicer filament id
+    pub fn mapping(&self) -> Vec<i32> {
+        let len = self
+            .matches
+            .iter()
+            .map(|m| m.filament.id)
+            .max()
+            .unwrap_or(0);
+        let mut mapping = vec![UNMAPPED_TRAY; len];
+
+        for m in &self.matches {
+            if let (Some(t), Some(i)) = (m.tray, m.filament.id.checked_sub(1)) {
+                mapping[i] = t;
+            }
+        }
+
+        mapping
+    }
+
+    /// Check whether the mapping can be used to print
+    pub fn is_ok(&self) -> bool {
+        self.issues().all(|(_, i)| !i.is_blocking())
+    }
+
+    /// Iterate over issues by filament
+    pub fn issues(&self) -> impl Iterator<Item = (&Filament, &MatchIssue)> {
+        self.matches
+            .iter()
+            .flat_map(|m| m.issues.iter().map(move |i| (&m.filament, i)))
+    }
+}
+
+/// Collect loaded trays from the printer status
+pub fn loaded_trays(status: &PrintValue) -> Vec<LoadedTray> {
+    let mut trays = vec![];
+
+    for a in status.ams.iter().flat_map(|a| a.ams.iter()) {
+        let Ok(ams_id) = a.id.parse::<i32>() else {
+            continue;
+        };
+
+        for t in &a.tray {
+            let Ok(tray_id) = t.id.parse::<i32>() else {
+                continue;
+            };
+
+            trays.push(LoadedTray {
+                index: ams_id * 4 + tray_id,
+                info: t.info.clone(),
+            });
+        }
+    }
+
+    if let Some(t) = &status.vt_tray {
+        trays.push(LoadedTray {
+            index: EXTERNAL_TRAY,
+            info: t.info.clone(),
+        });
+    }
+
+    trays.retain(|t| !t.info.tray_type.is_empty());
+    trays
+}
+
+/// Propose a tray for each sliced filament
+///
+/// Trays must match the filament type, with the closest colour preferred.
+/// Each tray is used at most once unless no other matching tray is available.
+pub fn map_filaments(filaments: &[Filament], status: &PrintValue) -> AmsMapping {
+    let trays = loaded_trays(status);
+    let mut used = vec![];
+    let mut matches = vec![];
+
+    for f in filaments {
+        let mut candidates: Vec<_> = trays
+            .iter()
+            .filter(|t| same_type(&t.info.tray_type, &f.filament_type))
+            .map(|t| (t, color_distance(&f.color, &t.info.tray_color)))
+            .collect();
+
+        // Prefer unused trays, then the closest colour
+        candidates.sort_by(|(a, da), (b, db)| {
+            used.contains(&a.index)
+                .cmp(&used.contains(&b.index))
+                .then(da.unwrap_or(f32::MAX).total_cmp(&db.unwrap_or(f32::MAX)))
+        });
+
+        let (tray, issues) = match candidates.first() {
+            Some((t, _)) => {
+                used.push(t.index);
+
+                (Some(t.index), check_tray(f, t))
+            }
+            None => {
+                // Report the type of the closest colour match, if any
+                let closest = trays
+                    .iter()
+                    .filter(|t| !used.contains(&t.index))
+                    .min_by(|a, b| {
+                        let da = color_distance(&f.color, &a.info.tray_color);
+                        let db = color_distance(&f.color, &b.info.tray_color);
+                        da.unwrap_or(f32::MAX).total_cmp(&db.unwrap_or(f32::MAX))
+                    });
+
+                let issue = match closest {
+                    Some(t) => MatchIssue::TypeMismatch {
+                        expected: f.filament_type.clone(),
+                        found: t.info.tray_type.clone(),
+                    },
+                    None => MatchIssue::NoTray(f.filament_type.clone()),
+                };
+
+                (None, vec![issue])
+            }
+        };
+
+        matches.push(FilamentMatch {
+            filament: f.clone(),
+            tray,
+            issues,
+        });
+    }
+
+    AmsMapping { matches }
+}
+
+/// Check an existing `ams_mapping` against the loaded trays
+pub fn check_mapping(filaments: &[Filament], mapping: &[i32], status: &PrintValue) -> AmsMapping {
+    let trays = loaded_trays(status);
+
+    let matches = filaments
+        .iter()
+        .map(|f| {
+            let index =
+                f.id.checked_sub(1)
+                    .and_then(|i| mapping.get(i))
+                    .copied()
+                    .unwrap_or(UNMAPPED_TRAY);
+
+            let (tray, issues) = match trays.iter().find(|t| t.index == index) {
+                Some(t) => (Some(index), check_tray(f, t)),
+                None => (None, vec![MatchIssue::NoTray(f.filament_type.clone())]),
+            };
+
+            FilamentMatch {
+                filament: f.clone(),
+                tray,
+                issues,
+            }
+        })
+        .collect();
+
+    AmsMapping { matches }
+}
+
+/// Check a tray for type, colour and remaining filament against a sliced filament
+pub fn check_tray(f: &Filament, t: &LoadedTray) -> Vec<MatchIssue> {
+    let mut issues = vec![];
+
+    if !same_type(&t.info.tray_type, &f.filament_type) {
+        issues.push(MatchIssue::TypeMismatch {
+            expected: f.filament_type.clone(),
+            found: t.info.tray_type.clone(),
+        });
+    }
+
+    let d = color_distance(&f.color, &t.info.tray_color);
+    if d.map(|d| d > COLOR_TOLERANCE).unwrap_or(true) {
+        issues.push(MatchIssue::ColorMismatch {
+            expected: f.color.clone(),
+            found: format!("#{}", t.info.tray_color.get(..6).unwrap_or("")),
+        });
+    }
+
+    match t.remaining() {
+        Some(available) if available < f.used_g => issues.push(MatchIssue::LowFilament {
+            required: f.used_g,
+            available,
+        }),
+        Some(_) => (),
+        None => issues.push(MatchIssue::UnknownRemaining),
+    }
+
+    issues
+}
+
+/// Compare filament types, ignoring case and surrounding whitespace
+///
+/// Variants are distinct materials (e.g. `PLA-CF`, or `PLA-S` support) so must match exactly.
+fn same_type(a: &str, b: &str) -> bool {
+    let a = a.trim();
+
+    !a.is_empty() && a.eq_ignore_ascii_case(b.trim())
+}
+
+/// Parse `#RRGGBB` or `RRGGBBAA` colours
+fn parse_color(s: &str) -> Option<[u8; 3]> {
+    let s = s.trim().trim_start_matches('#');
+    let c = |i: usize| u8::from_str_radix(s.get(i..i + 2)?, 16).ok();
+
+    Some([c(0)?, c(2)?, c(4)?])
+}
+
+/// RGB distance between two colours
+fn color_distance(a: &str, b: &str) -> Option<f32> {
+    let (a, b) = (parse_color(a)?, parse_color(b)?);
+
+    let d = a
+        .iter()
+        .zip(b.iter())
+        .map(|(a, b)| (*a as f32 - *b as f32).powi(2))
+        .sum::<f32>();
+
+    Some(d.sqrt())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::types::{Ams, AmsInfo, Tray};
+
+    fn tray(id: &str, tray_type: &str, color: &str, remain: isize) -> Tray {
+        Tray {
+            id: id.to_string(),
+            info: TrayInfo {
+                tray_type: tray_type.to_string(),
+                tray_color: color.to_string(),
+                tray_weight: "1000".to_string(),
+                remain,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn filament(id: usize, filament_type: &str, color: &str, used_g: f32) -> Filament {
+        Filament {
+            id,
+            filament_type: filament_type.to_string(),
+            color: color.to_string(),
+            used_g,
+            ..Default::default()
+        }
+    }
+
+    fn status() -> PrintValue {
+        PrintValue {
+            ams: Some(Ams {
+                ams: vec![AmsInfo {
+                    id: "0".to_string(),
+                    tray: vec![
+                        tray("0", "PLA", "FFFFFFFF", 80),
+                        tray("1", "PLA", "000000FF", 5),
+                        tray("2", "PETG", "0A2989FF", -1),
+                        tray("3", "", "", 0),
+                    ],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn map_by_type_and_colour() {
+        let filaments = &[
+            filament(1, "PLA", "#000000", 20.0),
+            filament(2, "PLA", "#F0F0F0", 100.0),
+            filament(4, "PETG", "#0A2989", 1.0),
+        ];
+
+        let m = map_filaments(filaments, &status());
+
+        assert_eq!(m.mapping(), vec![1, 0, -1, 2]);
+        assert_eq!(m.matches[0].issues, vec![]);
+        assert_eq!(m.matches[1].issues, vec![]);
+        assert_eq!(m.matches[2].issues, vec![MatchIssue::UnknownRemaining]);
+        assert!(m.is_ok());
+    }
+
+    #[test]
+    fn flag_mismatches() {
+        let filaments = &[
+            filament(1, "PLA", "#000000", 200.0),
+            filament(2, "ABS", "#FFFFFF", 10.0),
+            filament(3, "PLA", "#FF0000", 10.0),
+        ];
+
+        let m = map_filaments(filaments, &status());
+
+        assert_eq!(
+            m.matches[0].issues,
+            vec![MatchIssue::LowFilament {
+                required: 200.0,
+                available: 50.0
+            }]
+        );
+        assert_eq!(m.matches[1].tray, None);
+        assert!(matches!(
+            m.matches[1].issues[..],
+            [MatchIssue::TypeMismatch { .. }]
+        ));
+        assert!(matches!(
+            m.matches[2].issues[..],
+            [MatchIssue::ColorMismatch { .. }]
+        ));
+        assert!(!m.is_ok());
+    }
+
+    #[test]
+    fn check_existing_mapping() {
+        let filaments = &[
+            filament(1, "PLA", "#FFFFFF", 10.0),
+            filament(2, "PLA", "#000000", 10.0),
+        ];
+
+        let m = check_mapping(filaments, &[0, 2], &status());
+
+        assert_eq!(m.matches[0].issues, vec![]);
+        assert!(matches!(
+            m.matches[1].issues[..],
+            [
+                MatchIssue::TypeMismatch { .. },
+                MatchIssue::ColorMismatch { .. },
+                MatchIssue::UnknownRemaining
+            ]
+        ));
+
+        let m = check_mapping(filaments, &[0], &status());
+        assert_eq!(
+            m.matches[1].issues,
+            vec![MatchIssue::NoTray("PLA".to_string())]
+        );
+    }
+
+    #[test]
+    fn type_variants() {
+        assert!(same_type("PLA", " pla "));
+        assert!(same_type("PA-CF", "pa-cf"));
+        assert!(!same_type("", ""));
+
+        for (a, b) in [("PLA", "PLA-CF"), ("PLA", "PLA-S"), ("PA", "PA-CF")] {
+            assert!(!same_type(a, b), "{a} / {b}");
+
+            let t = LoadedTray {
+                index: 0,
+                info: tray("0", a, "FFFFFFFF", 80).info,
+            };
+            assert!(matches!(
+                check_tray(&filament(1, b, "#FFFFFF", 10.0), &t)[..],
+                [MatchIssue::TypeMismatch { .. }]
+            ));
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use crate::Error;
+
+/// Printer fans
+#[derive(Clone, Copy, PartialEq, Eq, Debug, displaydoc::Display, clap::ValueEnum)]
+pub enum Fan {
+    /// part cooling fan
+    Part,
+    /// aux fan
+    Aux,
+    /// chamber fan
+    Chamber,
+}
+
+impl Fan {
+    /// Fan index for `M106 P`
+    pub fn index(&self) -> u8 {
+        match self {
+            Self::Part => 1,
+            Self::Aux => 2,
+            Self::Chamber => 3,
+        }
+    }
+}
+
+/// Build the G-code to set a fan speed (%)
+pub fn fan_gcode(fan: Fan, percent: u8) -> Result<String, Error> {
+    if percent > 100 {
+        return Err(Error::InvalidFanSpeed(percent));
+    }
+
+    let pwm = (percent as u32 * 255 + 50) / 100;
+
+    Ok(format!("M106 P{} S{pwm}", fan.index()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn gcode() {
+        assert_eq!(fan_gcode(Fan::Part, 100).unwrap(), "M106 P1 S255");
+        assert_eq!(fan_gcode(Fan::Aux, 50).unwrap(), "M106 P2 S128");
+        assert_eq!(fan_gcode(Fan::Chamber, 0).unwrap(), "M106 P3 S0");
+        assert!(fan_gcode(Fan::Chamber, 101).is_err());
+    }
+}
@@ -0,0 +1,10 @@
+//! Printer control helpers, building and validating G-code for [crate::Printer] commands
+
+mod fan;
+pub use fan::*;
+
+mod motion;
+pub use motion::*;
+
+mod temperature;
+pub use temperature::*;
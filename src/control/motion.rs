@@ -0,0 +1,162 @@
+use std::fmt::Write;
+
+use crate::Error;
+
+/// Default jog feedrate (mm/min)
+pub const JOG_FEEDRATE: f32 = 3000.0;
+
+/// Default bed movement feedrate (mm/min)
+pub const BED_FEEDRATE: f32 = 600.0;
+
+/// Default extrusion feedrate (mm/min)
+pub const EXTRUDE_FEEDRATE: f32 = 300.0;
+
+/// Minimum nozzle temperature for extrusion (°C)
+pub const MIN_EXTRUDE_TEMP: f32 = 170.0;
+
+/// Maximum single move distance (mm)
+pub const MAX_MOVE: f32 = 256.0;
+
+/// Motion axes
+#[derive(Clone, Copy, PartialEq, Eq, Debug, displaydoc::Display, clap::ValueEnum)]
+pub enum Axis {
+    /// X
+    X,
+    /// Y
+    Y,
+    /// Z
+    Z,
+}
+
+/// Maintenance motion commands
+#[derive(Clone, PartialEq, Debug)]
+pub enum Motion {
+    /// Home the provided axes, or all axes if empty
+    Home(Vec<Axis>),
+    /// Move an axis by a relative distance (mm)
+    Jog {
+        axis: Axis,
+        distance: f32,
+        feedrate: Option<f32>,
+    },
+    /// Move the bed by a relative distance (mm), positive values move away from the nozzle
+    MoveBed { distance: f32 },
+    /// Extrude (positive) or retract (negative) filament (mm), requires a nozzle at [MIN_EXTRUDE_TEMP]
+    Extrude { length: f32, feedrate: Option<f32> },
+}
+
+impl Motion {
+    /// Build the G-code for this motion
+    pub fn gcode(&self) -> Result<String, Error> {
+        let mut s = String::new();
+
+        match self {
+            Self::Home(axes) => {
+                s.push_str("G28");
+                for a in axes {
+                    let _ = write!(s, " {a}");
+                }
+            }
+            Self::Jog {
+                axis,
+                distance,
+                feedrate,
+            } => {
+                let d = check_distance(*distance)?;
+                let f = check_feedrate(feedrate.unwrap_or(JOG_FEEDRATE))?;
+
+                relative_move(&mut s, &format!("{axis}{d:.2}"), f);
+            }
+            Self::MoveBed { distance } => {
+                let d = check_distance(*distance)?;
+
+                relative_move(&mut s, &format!("Z{d:.2}"), BED_FEEDRATE);
+            }
+            Self::Extrude { length, feedrate } => {
+                let l = check_distance(*length)?;
+                let f = check_feedrate(feedrate.unwrap_or(EXTRUDE_FEEDRATE))?;
+
+                // Relative extrusion, restoring absolute extrusion afterwards
+                let _ = write!(s, "M83\nG1 E{l:.2} F{f:.0}\nM82");
+            }
+        }
+
+        Ok(s)
+    }
+}
+
+/// Write a relative move, restoring the previous positioning mode afterwards
+fn relative_move(s: &mut String, target: &str, feedrate: f32) {
+    let _ = write!(
+        s,
+        "M1002 push_ref_mode\nG91\nG1 {target} F{feedrate:.0}\nM1002 pop_ref_mode"
+    );
+}
+
+fn check_distance(d: f32) -> Result<f32, Error> {
+    match d.is_finite() && d.abs() <= MAX_MOVE {
+        true => Ok(d),
+        false => Err(Error::InvalidMotion(format!(
+            "distance {d} outside ±{MAX_MOVE}mm"
+        ))),
+    }
+}
+
+fn check_feedrate(f: f32) -> Result<f32, Error> {
+    match f.is_finite() && f > 0.0 {
+        true => Ok(f),
+        false => Err(Error::InvalidMotion(format!("invalid feedrate {f}"))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn gcode() {
+        let tests = &[
+            (Motion::Home(vec![]), "G28"),
+            (Motion::Home(vec![Axis::X, Axis::Y]), "G28 X Y"),
+            (
+                Motion::Jog {
+                    axis: Axis::X,
+                    distance: -10.0,
+                    feedrate: None,
+                },
+                "M1002 push_ref_mode\nG91\nG1 X-10.00 F3000\nM1002 pop_ref_mode",
+            ),
+            (
+                Motion::MoveBed { distance: 5.0 },
+                "M1002 push_ref_mode\nG91\nG1 Z5.00 F600\nM1002 pop_ref_mode",
+            ),
+            (
+                Motion::Extrude {
+                    length: -2.0,
+                    feedrate: Some(120.0),
+                },
+                "M83\nG1 E-2.00 F120\nM82",
+            ),
+        ];
+
+        for (m, g) in tests {
+            assert_eq!(&m.gcode().unwrap(), g);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let m = Motion::Jog {
+            axis: Axis::Z,
+            distance: 1000.0,
+            feedrate: None,
+        };
+        assert!(m.gcode().is_err());
+
+        let m = Motion::Extrude {
+            length: 1.0,
+            feedrate: Some(0.0),
+        };
+        assert!(m.gcode().is_err());
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{model::Model, types::TrayInfo, Error};
+
+/// Printer heaters
+#[derive(Clone, Copy, PartialEq, Eq, Debug, displaydoc::Display, clap::ValueEnum)]
+pub enum Heater {
+    /// nozzle
+    Nozzle,
+    /// bed
+    Bed,
+    /// chamber
+    Chamber,
+}
+
+/// Allowed target range for a heater
+#[derive(Clone, PartialEq, Debug)]
+pub struct TempRange {
+    pub min: f32,
+    pub max: f32,
+    /// Origin of the limit, used for error reporting
+    pub source: String,
+}
+
+impl TempRange {
+    fn new(min: f32, max: f32, source: impl ToString) -> Self {
+        Self {
+            min,
+            max,
+            source: source.to_string(),
+        }
+    }
+}
+
+/// Temperature limits for a printer and loaded filament
+#[derive(Clone, PartialEq, Debug)]
+pub struct TempLimits {
+    pub nozzle: Vec<TempRange>,
+    pub bed: Vec<TempRange>,
+    pub chamber: Vec<TempRange>,
+}
+
+impl TempLimits {
+    /// Create limits for the provided printer model
+    pub fn for_model(model: Model) -> Self {
+        Self {
+            nozzle: vec![TempRange::new(0.0, model.max_nozzle_temp(), model)],
+            bed: vec![TempRange::new(0.0, model.max_bed_temp(), model)],
+            chamber: model
+                .max_chamber_temp()
+                .map(|t| vec![TempRange::new(0.0, t, model)])
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Restrict nozzle temperatures to the range supported by the tray's filament
+    pub fn with_tray(mut self, tray: &TrayInfo) -> Self {
+        let min = tray.nozzle_temp_min.parse::<f32>();
+        let max = tray.nozzle_temp_max.parse::<f32>();
+
+        if let (Ok(min), Ok(max)) = (min, max) {
+            let source = match tray.tray_type.as_str() {
+                "" => "filament".to_string(),
+                t => format!("{t} filament"),
+            };
+            self.nozzle.push(TempRange::new(min, max, source));
+        }
+
+        self
+    }
+
+    /// Check a heater target against these limits
+    ///
+    /// A target of `0` (heater off) is always allowed.
+    pub fn check(&self, heater: Heater, target: f32) -> Result<(), Error> {
+        let ranges = match heater {
+            Heater::Nozzle => &self.nozzle,
+            Heater::Bed => &self.bed,
+            Heater::Chamber => &self.chamber,
+        };
+
+        if ranges.is_empty() {
+            return Err(Error::UnsupportedHeater(heater));
+        }
+
+        if target == 0.0 {
+            return Ok(());
+        }
+
+        for r in ranges {
+            if !target.is_finite() || target < r.min || target > r.max {
+                return Err(Error::TemperatureOutOfRange {
+                    heater,
+                    target,
+                    min: r.min,
+                    max: r.max,
+                    limit: r.source.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Build the G-code to set a heater target (°C)
+pub fn temperature_gcode(heater: Heater, target: f32) -> String {
+    let cmd = match heater {
+        Heater::Nozzle => "M104",
+        Heater::Bed => "M140",
+        Heater::Chamber => "M141",
+    };
+
+    format!("{cmd} S{:.0}", target)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pla() -> TrayInfo {
+        TrayInfo {
+            nozzle_temp_min: "190".to_string(),
+            nozzle_temp_max: "230".to_string(),
+            tray_type: "PLA".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn model_limits() {
+        let l = TempLimits::for_model(Model::P1S);
+
+        assert!(l.check(Heater::Nozzle, 300.0).is_ok());
+        assert!(l.check(Heater::Nozzle, 301.0).is_err());
+        assert!(l.check(Heater::Bed, 100.0).is_ok());
+        assert!(l.check(Heater::Bed, 110.0).is_err());
+        assert!(matches!(
+            l.check(Heater::Chamber, 40.0),
+            Err(Error::UnsupportedHeater(Heater::Chamber))
+        ));
+
+        let l = TempLimits::for_model(Model::X1E);
+        assert!(l.check(Heater::Chamber, 60.0).is_ok());
+    }
+
+    #[test]
+    fn tray_limits() {
+        let l = TempLimits::for_model(Model::X1C).with_tray(&pla());
+
+        assert!(l.check(Heater::Nozzle, 220.0).is_ok());
+        assert!(l.check(Heater::Nozzle, 0.0).is_ok());
+        assert!(l.check(Heater::Bed, 60.0).is_ok());
+
+        match l.check(Heater::Nozzle, 250.0) {
+            Err(e @ Error::TemperatureOutOfRange { .. }) => {
+                assert_eq!(
+                    e.to_string(),
+                    "nozzle target 250°C outside PLA filament range 190-230°C"
+                );
+            }
+            r => panic!("unexpected result: {r:?}"),
+        }
+
+        assert!(l.check(Heater::Nozzle, 180.0).is_err());
+    }
+
+    #[test]
+    fn gcode() {
+        assert_eq!(temperature_gcode(Heater::Nozzle, 220.0), "M104 S220");
+        assert_eq!(temperature_gcode(Heater::Bed, 60.0), "M140 S60");
+        assert_eq!(temperature_gcode(Heater::Chamber, 45.0), "M141 S45");
+    }
+}
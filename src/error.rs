@@ -1,11 +1,59 @@
 use paho_mqtt::Error as MqttError;
 
+use crate::{control::Heater, types::GcodeState};
+
 #[derive(Debug, thiserror::Error, displaydoc::Display)]
 pub enum Error {
     /// MQTT error {0}
     Mqtt(MqttError),
+    /// JSON error {0}
+    Json(serde_json::Error),
+    /// IO error {0}
+    Io(std::io::Error),
+    /// FTP error {code}: {message}
+    Ftp { code: u16, message: String },
     /// Channel send error
     SendError,
+    /// Printer serial unknown, set `serial` or wait for a report
+    UnknownSerial,
+    /// Printer model unknown
+    UnknownModel,
+    /// {0} temperature control is not supported on this printer
+    UnsupportedHeater(Heater),
+    /// {heater} target {target}°C outside {limit} range {min}-{max}°C
+    TemperatureOutOfRange {
+        heater: Heater,
+        target: f32,
+        min: f32,
+        max: f32,
+        limit: String,
+    },
+    /// Invalid fan speed {0}%, expected 0-100%
+    InvalidFanSpeed(u8),
+    /// Invalid motion: {0}
+    InvalidMotion(String),
+    /// Nozzle at {temp}°C, extrusion requires at least {min}°C
+    ColdExtrusion { temp: f32, min: f32 },
+    /// Printer status unknown, no report received
+    UnknownStatus,
+    /// Printer busy ({0})
+    PrinterBusy(GcodeState),
+    /// No active print ({0})
+    NotPrinting(GcodeState),
+    /// Timeout waiting for printer
+    Timeout,
+    /// Invalid print job: {0}
+    InvalidJob(String),
+    /// Job incompatible with printer: {0}
+    IncompatibleJob(String),
+    /// Zip error {0}
+    Zip(zip::result::ZipError),
+    /// Invalid 3MF package: {0}
+    ThreeMf(String),
+    /// Incomplete levelling grid, {missing} of {nodes} points missing
+    IncompleteGrid { missing: usize, nodes: usize },
+    /// Invalid printer serial '{0}'
+    InvalidSerial(String),
 }
 
 impl From<MqttError> for Error {
@@ -13,3 +61,21 @@ impl From<MqttError> for Error {
         Self::Mqtt(value)
     }
 }
+
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Json(value)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<zip::result::ZipError> for Error {
+    fn from(value: zip::result::ZipError) -> Self {
+        Self::Zip(value)
+    }
+}
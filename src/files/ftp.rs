@@ -0,0 +1,343 @@
+//! Minimal implicit FTPS protocol implementation
+
+use std::{sync::Arc, time::Duration};
+
+use rustls::{ClientConfig, ServerName};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
+    net::TcpStream,
+};
+use tokio_rustls::{client::TlsStream, TlsConnector};
+use tracing::trace;
+
+use crate::{printer::NullTlsVerifier, Error};
+
+pub type Stream = TlsStream<TcpStream>;
+
+/// Timeout for the completion reply following a failed transfer
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// FTP server reply
+#[derive(Clone, PartialEq, Debug)]
+pub struct Reply {
+    pub code: u16,
+    pub message: String,
+}
+
+impl Reply {
+    /// Convert unexpected reply codes to errors
+    pub fn expect(self, codes: &[u16]) -> Result<Self, Error> {
+        match codes.contains(&self.code) {
+            true => Ok(self),
+            false => Err(Error::Ftp {
+                code: self.code,
+                message: self.message,
+            }),
+        }
+    }
+}
+
+/// TLS connector, shared between control and data connections for session reuse
+#[derive(Clone)]
+pub struct Connector {
+    host: String,
+    port: u16,
+    name: ServerName,
+    tls: TlsConnector,
+}
+
+impl Connector {
+    pub fn new(host: &str, port: u16) -> Result<Self, Error> {
+        let config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(NullTlsVerifier))
+            .with_no_client_auth();
+
+        let name = ServerName::try_from(host).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("invalid hostname: {host}"),
+            )
+        })?;
+
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            name,
+            tls: TlsConnector::from(Arc::new(config)),
+        })
+    }
+
+    /// Open a TLS connection to the server control port
+    pub async fn connect(&self) -> Result<Stream, Error> {
+        let tcp = self.tcp(self.port).await?;
+
+        self.tls(tcp).await
+    }
+
+    /// Open a TCP connection to the server
+    pub async fn tcp(&self, port: u16) -> Result<TcpStream, Error> {
+        trace!("Connecting to {}:{port}", self.host);
+
+        let tcp = TcpStream::connect((self.host.as_str(), port)).await?;
+
+        Ok(tcp)
+    }
+
+    /// Start a TLS session on a TCP connection
+    pub async fn tls(&self, tcp: TcpStream) -> Result<Stream, Error> {
+        let tls = self.tls.connect(self.name.clone(), tcp).await?;
+
+        Ok(tls)
+    }
+}
+
+/// FTP control connection
+pub struct Control<S = Stream> {
+    stream: BufReader<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Control<S> {
+    /// Wrap a connected stream and read the server greeting
+    pub async fn new(stream: S) -> Result<Self, Error> {
+        let mut c = Self {
+            stream: BufReader::new(stream),
+        };
+
+        c.reply().await?.expect(&[220])?;
+
+        Ok(c)
+    }
+
+    /// Send a command and read the reply
+    pub async fn command(&mut self, cmd: &str) -> Result<Reply, Error> {
+        match cmd.starts_with("PASS ") {
+            true => trace!("> PASS ****"),
+            false => trace!("> {cmd}"),
+        }
+
+        let s = self.stream.get_mut();
+        s.write_all(format!("{cmd}\r\n").as_bytes()).await?;
+        s.flush().await?;
+
+        self.reply().await
+    }
+
+    /// Read the reply completing a data transfer
+    ///
+    /// The reply is read even if the transfer failed so the control connection stays in step
+    /// with the server, in which case the transfer error is returned.
+    pub async fn finish<T>(&mut self, transfer: Result<T, Error>) -> Result<T, Error> {
+        match transfer {
+            Ok(v) => {
+                self.reply().await?.expect(&[226, 250])?;
+                Ok(v)
+            }
+            Err(e) => {
+                match tokio::time::timeout(DRAIN_TIMEOUT, self.reply()).await {
+                    Ok(Ok(r)) => trace!("Transfer failed with reply {} {}", r.code, r.message),
+                    Ok(Err(e)) => trace!("Failed to read transfer reply: {e}"),
+                    Err(_) => trace!("Timeout waiting for transfer reply"),
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Read a (possibly multi-line) reply
+    pub async fn reply(&mut self) -> Result<Reply, Error> {
+        let mut line = String::new();
+        let mut message = String::new();
+        let mut code = None;
+
+        loop {
+            line.clear();
+            if self.stream.read_line(&mut line).await? == 0 {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+
+            trace!("< {}", line.trim_end());
+
+            // Continuation lines within multi-line replies may not start with a code
+            let c = line.get(..3).and_then(|c| c.parse::<u16>().ok());
+            let (c, sep, text) = match (c, line.get(3..4)) {
+                (Some(c), Some(sep @ (" " | "-"))) => (Some(c), sep, line[4..].trim_end()),
+                _ => (None, "", line.trim()),
+            };
+
+            if !message.is_empty() {
+                message.push('\n');
+            }
+            message.push_str(text);
+
+            match (code, c) {
+                // Start of reply
+                (None, Some(c)) if sep == "-" => code = Some(c),
+                (None, Some(c)) => return Ok(Reply { code: c, message }),
+                // End of multi-line reply
+                (Some(expected), Some(c)) if c == expected && sep == " " => {
+                    return Ok(Reply { code: c, message })
+                }
+                (None, None) => {
+                    return Err(Error::Ftp {
+                        code: 0,
+                        message: format!("invalid reply: {}", line.trim_end()),
+                    })
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Parse the data port from a PASV (227) reply
+pub fn parse_pasv(message: &str) -> Option<u16> {
+    let start = message.find('(')?;
+    let end = message[start..].find(')')? + start;
+
+    let v: Vec<u16> = message[start + 1..end]
+        .split(',')
+        .map(|v| v.trim().parse::<u16>())
+        .collect::<Result<_, _>>()
+        .ok()?;
+
+    match v.as_slice() {
+        [_, _, _, _, p1, p2] if *p1 < 256 && *p2 < 256 => Some(p1 * 256 + p2),
+        _ => None,
+    }
+}
+
+/// Parse a unix-style LIST line
+///
+/// e.g. `-rw-rw-rw-   1 root  root  1234 Jan 01 12:00 model.gcode.3mf`
+pub fn parse_list_line(line: &str) -> Option<(String, u64, bool, String)> {
+    let mut parts = line.split_whitespace();
+
+    let perms = parts.next()?;
+    let _links = parts.next()?;
+    let _user = parts.next()?;
+    let _group = parts.next()?;
+    let size = parts.next()?.parse::<u64>().ok()?;
+    let modified = [parts.next()?, parts.next()?, parts.next()?].join(" ");
+
+    // Re-join remaining parts to preserve spaces in file names
+    let mut name = line;
+    for _ in 0..8 {
+        name = name.trim_start();
+        name = &name[name.find(char::is_whitespace)?..];
+    }
+    let name = name.trim();
+
+    if name.is_empty() || name == "." || name == ".." {
+        return None;
+    }
+
+    Some((name.to_string(), size, perms.starts_with('d'), modified))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use tokio::io::{duplex, AsyncReadExt, DuplexStream};
+
+    /// Control connection to an in-memory server sending the provided replies
+    async fn control(replies: &str) -> (Control<DuplexStream>, DuplexStream) {
+        let (client, mut server) = duplex(4096);
+        server
+            .write_all(format!("220 ready\r\n{replies}").as_bytes())
+            .await
+            .unwrap();
+
+        (Control::new(client).await.unwrap(), server)
+    }
+
+    #[tokio::test]
+    async fn replies() {
+        let (mut c, mut server) = control(concat!(
+            "331 Password required\r\n",
+            "211-Features:\r\n",
+            " SIZE\r\n",
+            "211-PASV\r\n",
+            "211 End\r\n",
+            "garbage\r\n",
+        ))
+        .await;
+
+        let r = c.command("USER bblp").await.unwrap();
+        assert_eq!(r.code, 331);
+        assert!(r.clone().expect(&[230]).is_err());
+
+        let mut sent = [0u8; 11];
+        server.read_exact(&mut sent).await.unwrap();
+        assert_eq!(&sent, b"USER bblp\r\n");
+
+        // Multi-line replies end at a matching code followed by a space
+        let r = c.reply().await.unwrap();
+        assert_eq!(r.code, 211);
+        assert_eq!(r.message, "Features:\nSIZE\nPASV\nEnd");
+
+        assert!(matches!(c.reply().await, Err(Error::Ftp { code: 0, .. })));
+
+        drop(server);
+        assert!(matches!(c.reply().await, Err(Error::Io(_))));
+    }
+
+    #[tokio::test]
+    async fn drain_failed_transfer() {
+        let (mut c, _server) = control(concat!(
+            "426 Transfer aborted\r\n",
+            "226 Transfer complete\r\n",
+            "200 OK\r\n",
+        ))
+        .await;
+
+        // The abort reply is consumed and the transfer error returned
+        let transfer: Result<u64, _> =
+            Err(std::io::Error::from(std::io::ErrorKind::ConnectionReset).into());
+        assert!(matches!(c.finish(transfer).await, Err(Error::Io(_))));
+
+        assert_eq!(c.finish(Ok(12)).await.unwrap(), 12);
+        assert_eq!(c.command("NOOP").await.unwrap().code, 200);
+    }
+
+    #[test]
+    fn pasv() {
+        assert_eq!(
+            parse_pasv("Entering Passive Mode (192,168,1,20,195,80)."),
+            Some(50000)
+        );
+        assert_eq!(parse_pasv("Entering Passive Mode"), None);
+        assert_eq!(parse_pasv("(1,2,3,4,256,0)"), None);
+    }
+
+    #[test]
+    fn list() {
+        let tests = &[
+            (
+                "-rw-rw-rw-   1 root  root   2746134 Aug 22 09:14 my bracket.gcode.3mf",
+                Some((
+                    "my bracket.gcode.3mf".to_string(),
+                    2746134,
+                    false,
+                    "Aug 22 09:14".to_string(),
+                )),
+            ),
+            (
+                "drwxrwxrwx   2 root  root      4096 Jan 01  2023 timelapse",
+                Some((
+                    "timelapse".to_string(),
+                    4096,
+                    true,
+                    "Jan 01 2023".to_string(),
+                )),
+            ),
+            ("drwxrwxrwx   2 root  root      4096 Jan 01  2023 ..", None),
+            ("total 12", None),
+        ];
+
+        for (l, e) in tests {
+            assert_eq!(&parse_list_line(l), e);
+        }
+    }
+}
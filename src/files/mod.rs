@@ -0,0 +1,301 @@
+//! Printer storage (SD card / internal storage) access via implicit FTPS
+//!
+//! Printers expose an FTPS server on [ConnectOpts::ftp_port] using the same
+//! `bblp` / access code credentials as the MQTT connection.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tracing::debug;
+
+use crate::{ConnectOpts, Error};
+
+mod ftp;
+use ftp::{Connector, Control};
+
+/// Transfer buffer size
+const BUFF_LEN: usize = 64 * 1024;
+
+/// File on printer storage
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct FileEntry {
+    /// File name
+    pub name: String,
+    /// Full path on printer storage
+    pub path: String,
+    /// File size in bytes
+    pub size: u64,
+    /// Entry is a directory
+    pub is_dir: bool,
+    /// Modification time as reported by the server
+    pub modified: String,
+}
+
+/// Transfer progress
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Progress {
+    /// Bytes transferred
+    pub transferred: u64,
+    /// Total bytes, if known
+    pub total: Option<u64>,
+}
+
+/// FTPS client for printer storage
+pub struct FileClient {
+    connector: Connector,
+    control: Control,
+}
+
+impl FileClient {
+    /// Connect and login to the printer's FTPS server
+    pub async fn connect(opts: &ConnectOpts) -> Result<Self, Error> {
+        debug!("Connecting to ftps://{}:{}", opts.hostname, opts.ftp_port);
+
+        let connector = Connector::new(&opts.hostname, opts.ftp_port)?;
+        let mut control = Control::new(connector.connect().await?).await?;
+
+        // Login
+        control.command("USER bblp").await?.expect(&[230, 331])?;
+        control
+            .command(&format!("PASS {}", opts.access_code))
+            .await?
+            .expect(&[230, 202])?;
+
+        // Enable encrypted data connections and binary transfers
+        control.command("PBSZ 0").await?.expect(&[200])?;
+        control.command("PROT P").await?.expect(&[200])?;
+        control.command("TYPE I").await?.expect(&[200])?;
+
+        Ok(Self { connector, control })
+    }
+
+    /// List files in a directory
+    pub async fn list(&mut self, dir: &str) -> Result<Vec<FileEntry>, Error> {
+        let mut buff = vec![];
+        self.retrieve(&format!("LIST {dir}"), &mut buff, None, |_| ())
+            .await?;
+
+        let base = dir.trim_end_matches('/');
+
+        let entries = String::from_utf8_lossy(&buff)
+            .lines()
+            .filter_map(ftp::parse_list_line)
+            .map(|(name, size, is_dir, modified)| FileEntry {
+                path: format!("{base}/{name}"),
+                name,
+                size,
+                is_dir,
+                modified,
+            })
+            .collect();
+
+        Ok(entries)
+    }
+
+    /// Fetch the size of a file
+    pub async fn size(&mut self, path: &str) -> Result<u64, Error> {
+        let r = self
+            .control
+            .command(&format!("SIZE {path}"))
+            .await?
+            .expect(&[213])?;
+
+        r.message.trim().parse::<u64>().map_err(|_| Error::Ftp {
+            code: r.code,
+            message: format!("invalid size: {}", r.message),
+        })
+    }
+
+    /// Download a file, returning the number of bytes written
+    pub async fn download<W: AsyncWrite + Unpin>(
+        &mut self,
+        path: &str,
+        w: &mut W,
+        progress: impl FnMut(Progress),
+    ) -> Result<u64, Error> {
+        let total = self.size(path).await.ok();
+
+        self.retrieve(&format!("RETR {path}"), w, total, progress)
+            .await
+    }
+
+    /// Download a file to the local filesystem
+    pub async fn download_file(
+        &mut self,
+        path: &str,
+        local: impl AsRef<Path>,
+        progress: impl FnMut(Progress),
+    ) -> Result<u64, Error> {
+        let mut f = tokio::fs::File::create(local).await?;
+        let n = self.download(path, &mut f, progress).await?;
+        f.flush().await?;
+
+        Ok(n)
+    }
+
+    /// Upload a file, returning the number of bytes sent
+    pub async fn upload<R: AsyncRead + Unpin>(
+        &mut self,
+        path: &str,
+        r: &mut R,
+        total: Option<u64>,
+        mut progress: impl FnMut(Progress),
+    ) -> Result<u64, Error> {
+        let mut data = self.open_data(&format!("STOR {path}")).await?;
+
+        let transfer = async {
+            let mut buff = vec![0u8; BUFF_LEN];
+            let mut transferred = 0;
+
+            loop {
+                let n = r.read(&mut buff).await?;
+                if n == 0 {
+                    break;
+                }
+
+                data.write_all(&buff[..n]).await?;
+
+                transferred += n as u64;
+                progress(Progress { transferred, total });
+            }
+
+            data.shutdown().await?;
+
+            Ok(transferred)
+        }
+        .await;
+        drop(data);
+
+        self.control.finish(transfer).await
+    }
+
+    /// Upload a file from the local filesystem
+    pub async fn upload_file(
+        &mut self,
+        local: impl AsRef<Path>,
+        path: &str,
+        progress: impl FnMut(Progress),
+    ) -> Result<u64, Error> {
+        let mut f = tokio::fs::File::open(local).await?;
+        let total = f.metadata().await?.len();
+
+        self.upload(path, &mut f, Some(total), progress).await
+    }
+
+    /// Delete a file
+    pub async fn delete(&mut self, path: &str) -> Result<(), Error> {
+        self.control
+            .command(&format!("DELE {path}"))
+            .await?
+            .expect(&[250])?;
+
+        Ok(())
+    }
+
+    /// Close the connection
+    pub async fn quit(mut self) -> Result<(), Error> {
+        self.control.command("QUIT").await?.expect(&[221])?;
+
+        Ok(())
+    }
+
+    /// Open a passive data connection and issue a transfer command
+    async fn open_data(&mut self, cmd: &str) -> Result<ftp::Stream, Error> {
+        let r = self.control.command("PASV").await?.expect(&[227])?;
+
+        // Use the control host rather than the reported address, which may be unreachable
+        let port = ftp::parse_pasv(&r.message).ok_or_else(|| Error::Ftp {
+            code: r.code,
+            message: format!("invalid PASV reply: {}", r.message),
+        })?;
+
+        let data = self.connector.tcp(port).await?;
+
+        self.control.command(cmd).await?.expect(&[125, 150])?;
+
+        // Servers start the data TLS session once the transfer command is accepted
+        match self.connector.tls(data).await {
+            Ok(s) => Ok(s),
+            Err(e) => self.control.finish(Err(e)).await,
+        }
+    }
+
+    /// Read a data connection to completion
+    async fn retrieve<W: AsyncWrite + Unpin>(
+        &mut self,
+        cmd: &str,
+        w: &mut W,
+        total: Option<u64>,
+        mut progress: impl FnMut(Progress),
+    ) -> Result<u64, Error> {
+        let mut data = self.open_data(cmd).await?;
+
+        let transfer = async {
+            let mut buff = vec![0u8; BUFF_LEN];
+            let mut transferred = 0;
+
+            loop {
+                let n = match data.read(&mut buff).await {
+                    Ok(0) => break,
+                    Ok(n) => n,
+                    // Servers may close data connections without a TLS close_notify
+                    Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                    Err(e) => return Err(e.into()),
+                };
+
+                w.write_all(&buff[..n]).await?;
+
+                transferred += n as u64;
+                progress(Progress { transferred, total });
+            }
+
+            Ok(transferred)
+        }
+        .await;
+        drop(data);
+
+        // Read the completion reply even on failure so later commands stay in sync
+        self.control.finish(transfer).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Round-trip test against a local implicit FTPS server
+    ///
+    /// Configure with `FTP_HOSTNAME`, `FTP_PORT` and `FTP_ACCESS_CODE` (used as the `bblp` password)
+    #[tokio::test]
+    #[ignore]
+    async fn local_server() {
+        let opts = ConnectOpts {
+            hostname: std::env::var("FTP_HOSTNAME").unwrap_or("localhost".to_string()),
+            ftp_port: std::env::var("FTP_PORT")
+                .map(|p| p.parse().unwrap())
+                .unwrap_or(990),
+            access_code: std::env::var("FTP_ACCESS_CODE").unwrap(),
+            ..Default::default()
+        };
+
+        let mut c = FileClient::connect(&opts).await.unwrap();
+
+        let data = b"G28\nM104 S220\n".to_vec();
+        let n = c
+            .upload("/test.gcode", &mut data.as_slice(), None, |_| ())
+            .await
+            .unwrap();
+        assert_eq!(n, data.len() as u64);
+
+        let files = c.list("/").await.unwrap();
+        assert!(files.iter().any(|f| f.name == "test.gcode"));
+
+        let mut buff = vec![];
+        c.download("/test.gcode", &mut buff, |_| ()).await.unwrap();
+        assert_eq!(buff, data);
+
+        c.delete("/test.gcode").await.unwrap();
+        c.quit().await.unwrap();
+    }
+}
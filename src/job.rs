@@ -0,0 +1,207 @@
+use crate::{
+    types::command::{BedType, ProjectFile},
+    Error,
+};
+
+/// AMS mapping value for the external spool holder
+pub const EXTERNAL_TRAY: i32 = 254;
+
+/// AMS mapping value for an unmapped filament
+pub const UNMAPPED_TRAY: i32 = -1;
+
+/// Maximum AMS tray index (4 AMS units with 4 trays each)
+pub const MAX_TRAY: i32 = 15;
+
+/// Print job for a project file on printer storage
+#[derive(Clone, PartialEq, Debug)]
+pub struct PrintJob {
+    /// Project file path on printer storage, e.g. `model.gcode.3mf`
+    pub file: String,
+    /// Plate index (1-based)
+    pub plate: usize,
+    /// Task name shown on the printer, defaults to the file name
+    pub name: Option<String>,
+    /// Expected MD5 of the plate G-code
+    pub md5: Option<String>,
+    /// Run auto bed levelling before printing
+    pub bed_leveling: bool,
+    /// Run flow (extrusion) calibration
+    pub flow_cali: bool,
+    /// Run vibration compensation calibration
+    pub vibration_cali: bool,
+    /// Enable first layer inspection
+    pub layer_inspect: bool,
+    /// Record a timelapse
+    pub timelapse: bool,
+    /// Build plate type
+    pub bed_type: BedType,
+    /// Tray for each sliced filament, or `None` to print from the external spool
+    pub ams_mapping: Option<Vec<i32>>,
+    /// User confirmed the build plate is clear
+    pub plate_clear: bool,
+}
+
+impl PrintJob {
+    /// Create a new print job for the first plate of a project file, with default options
+    pub fn new(file: impl ToString) -> Self {
+        Self {
+            file: file.to_string(),
+            plate: 1,
+            name: None,
+            md5: None,
+            bed_leveling: true,
+            flow_cali: true,
+            vibration_cali: true,
+            layer_inspect: true,
+            timelapse: false,
+            bed_type: BedType::Auto,
+            ams_mapping: None,
+            plate_clear: false,
+        }
+    }
+
+    /// Check job options are valid
+    pub fn validate(&self) -> Result<(), Error> {
+        let file = self.file.trim_start_matches('/');
+
+        if file.is_empty() {
+            return Err(Error::InvalidJob("no file specified".to_string()));
+        }
+
+        if !file.ends_with(".3mf") {
+            return Err(Error::InvalidJob(format!(
+                "{file} is not a .3mf project file"
+            )));
+        }
+
+        if self.plate == 0 {
+            return Err(Error::InvalidJob("plate indices start at 1".to_string()));
+        }
+
+        if let Some(m) = &self.ams_mapping {
+            if m.is_empty() {
+                return Err(Error::InvalidJob("empty AMS mapping".to_string()));
+            }
+
+            if let Some(t) = m
+                .iter()
+                .find(|t| !(UNMAPPED_TRAY..=MAX_TRAY).contains(*t) && **t != EXTERNAL_TRAY)
+            {
+                return Err(Error::InvalidJob(format!("invalid AMS tray {t}")));
+            }
+
+            if m.iter().all(|t| *t == UNMAPPED_TRAY) {
+                return Err(Error::InvalidJob("no filaments mapped".to_string()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build the `project_file` request for this job
+    pub fn project_file(&self) -> ProjectFile {
+        let file = self.file.trim_start_matches('/');
+        let name = self.name.clone().unwrap_or_else(|| {
+            let f = file.rsplit('/').next().unwrap_or(file);
+            f.trim_end_matches(".3mf")
+                .trim_end_matches(".gcode")
+                .to_string()
+        });
+
+        ProjectFile {
+            param: format!("Metadata/plate_{}.gcode", self.plate),
+            url: format!("file:///sdcard/{file}"),
+            subtask_name: name,
+            md5: self.md5.clone().unwrap_or_default(),
+            project_id: "0".to_string(),
+            profile_id: "0".to_string(),
+            task_id: "0".to_string(),
+            subtask_id: "0".to_string(),
+            bed_type: self.bed_type,
+            bed_leveling: self.bed_leveling,
+            flow_cali: self.flow_cali,
+            vibration_cali: self.vibration_cali,
+            layer_inspect: self.layer_inspect,
+            timelapse: self.timelapse,
+            use_ams: self.ams_mapping.is_some(),
+            ams_mapping: self.ams_mapping.clone().unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use serde_json::json;
+
+    use crate::types::command::{Command, PrintCommand};
+
+    #[test]
+    fn encode_project_file() {
+        let job = PrintJob {
+            plate: 2,
+            timelapse: true,
+            bed_type: BedType::TexturedPlate,
+            ams_mapping: Some(vec![0, 2, -1]),
+            ..PrintJob::new("jobs/bracket.gcode.3mf")
+        };
+        job.validate().unwrap();
+
+        let c = Command::print(7, PrintCommand::ProjectFile(Box::new(job.project_file())));
+
+        assert_eq!(
+            serde_json::to_value(c).unwrap(),
+            json!({"print": {
+                "sequence_id": "7",
+                "command": "project_file",
+                "param": "Metadata/plate_2.gcode",
+                "url": "file:///sdcard/jobs/bracket.gcode.3mf",
+                "subtask_name": "bracket",
+                "md5": "",
+                "project_id": "0",
+                "profile_id": "0",
+                "task_id": "0",
+                "subtask_id": "0",
+                "bed_type": "textured_plate",
+                "bed_leveling": true,
+                "flow_cali": true,
+                "vibration_cali": true,
+                "layer_inspect": true,
+                "timelapse": true,
+                "use_ams": true,
+                "ams_mapping": [0, 2, -1],
+            }})
+        );
+    }
+
+    #[test]
+    fn validate_job() {
+        let invalid = &[
+            PrintJob::new(""),
+            PrintJob::new("model.stl"),
+            PrintJob {
+                plate: 0,
+                ..PrintJob::new("model.3mf")
+            },
+            PrintJob {
+                ams_mapping: Some(vec![16]),
+                ..PrintJob::new("model.3mf")
+            },
+            PrintJob {
+                ams_mapping: Some(vec![-1, -1]),
+                ..PrintJob::new("model.3mf")
+            },
+        ];
+
+        for j in invalid {
+            assert!(j.validate().is_err(), "{j:?}");
+        }
+
+        let j = PrintJob {
+            ams_mapping: Some(vec![EXTERNAL_TRAY]),
+            ..PrintJob::new("model.3mf")
+        };
+        assert!(j.validate().is_ok());
+    }
+}
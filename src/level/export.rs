@@ -0,0 +1,312 @@
+//! [LevelMap] export formats, for use with other firmware and CAD tooling
+
+use std::io::Write;
+
+use super::{LevelMap, Point};
+use crate::Error;
+
+/// Bed mesh export format
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, clap::ValueEnum)]
+pub enum MeshFormat {
+    /// Terminal grid
+    #[default]
+    Grid,
+    /// CSV with `x,y,z,d` columns
+    Csv,
+    /// JSON [LevelMap]
+    Json,
+    /// Klipper `[bed_mesh]` profile
+    Klipper,
+    /// Marlin `M421` mesh script
+    Marlin,
+    /// Wavefront OBJ surface
+    Obj,
+    /// ASCII STL surface
+    Stl,
+}
+
+impl LevelMap {
+    /// Write the mesh in the specified format
+    pub fn export(&self, format: MeshFormat, w: &mut impl Write) -> Result<(), Error> {
+        match format {
+            MeshFormat::Grid => write!(w, "{self}")?,
+            MeshFormat::Csv => self.write_csv(w)?,
+            MeshFormat::Json => {
+                serde_json::to_writer_pretty(&mut *w, self)?;
+                writeln!(w)?;
+            }
+            MeshFormat::Klipper => self.write_klipper(w)?,
+            MeshFormat::Marlin => self.write_marlin(w)?,
+            MeshFormat::Obj => self.write_obj(w)?,
+            MeshFormat::Stl => self.write_stl(w)?,
+        }
+
+        Ok(())
+    }
+
+    /// Write points as CSV
+    pub fn write_csv(&self, w: &mut impl Write) -> Result<(), Error> {
+        writeln!(w, "x,y,z,d")?;
+        for p in &self.points {
+            writeln!(w, "{},{},{},{}", p.x, p.y, p.c, p.d)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write a Klipper `[bed_mesh default]` profile, requires a full grid
+    pub fn write_klipper(&self, w: &mut impl Write) -> Result<(), Error> {
+        let rows = self.rows()?;
+        let (x, y) = (bounds(&self.xs), bounds(&self.ys));
+
+        writeln!(w, "[bed_mesh default]")?;
+        writeln!(w, "version = 1")?;
+        writeln!(w, "points =")?;
+        for r in rows {
+            let r: Vec<_> = r.iter().map(|p| format!("{:.06}", p.c)).collect();
+            writeln!(w, "\t{}", r.join(", "))?;
+        }
+        writeln!(w, "x_count = {}", self.xs.len())?;
+        writeln!(w, "y_count = {}", self.ys.len())?;
+        writeln!(w, "mesh_x_pps = 0")?;
+        writeln!(w, "mesh_y_pps = 0")?;
+        writeln!(w, "algo = lagrange")?;
+        writeln!(w, "tension = 0.2")?;
+        writeln!(w, "min_x = {}", x.0)?;
+        writeln!(w, "max_x = {}", x.1)?;
+        writeln!(w, "min_y = {}", y.0)?;
+        writeln!(w, "max_y = {}", y.1)?;
+
+        Ok(())
+    }
+
+    /// Write a Marlin `M421` script setting each measured mesh point by index
+    pub fn write_marlin(&self, w: &mut impl Write) -> Result<(), Error> {
+        writeln!(
+            w,
+            "; {}x{} mesh, X {:?}, Y {:?}",
+            self.xs.len(),
+            self.ys.len(),
+            bounds(&self.xs),
+            bounds(&self.ys)
+        )?;
+
+        for (j, y) in self.ys.iter().enumerate() {
+            for (i, x) in self.xs.iter().enumerate() {
+                if let Some(p) = self.value(*x, *y) {
+                    writeln!(w, "M421 I{i} J{j} Z{:.03}", p.c)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write a Wavefront OBJ surface (mm), skipping cells with missing points
+    pub fn write_obj(&self, w: &mut impl Write) -> Result<(), Error> {
+        writeln!(w, "# bed mesh")?;
+
+        // Vertices are numbered from 1 in grid order
+        let mut index = vec![None; self.xs.len() * self.ys.len()];
+        let mut n = 0;
+        for (j, y) in self.ys.iter().enumerate() {
+            for (i, x) in self.xs.iter().enumerate() {
+                if let Some(p) = self.value(*x, *y) {
+                    n += 1;
+                    index[j * self.xs.len() + i] = Some(n);
+                    writeln!(w, "v {} {} {}", p.x, p.y, p.c)?;
+                }
+            }
+        }
+
+        for [a, b, c] in self.triangles() {
+            let v = |p: &Point| {
+                let i = self.xs.iter().position(|x| *x == p.x)?;
+                let j = self.ys.iter().position(|y| *y == p.y)?;
+                index[j * self.xs.len() + i]
+            };
+
+            if let (Some(a), Some(b), Some(c)) = (v(a), v(b), v(c)) {
+                writeln!(w, "f {a} {b} {c}")?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write an ASCII STL surface (mm), skipping cells with missing points
+    pub fn write_stl(&self, w: &mut impl Write) -> Result<(), Error> {
+        writeln!(w, "solid bed_mesh")?;
+
+        for t in self.triangles() {
+            let [a, b, c] = t.map(|p| [p.x, p.y, p.c]);
+            let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+            let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+            let n = [
+                u[1] * v[2] - u[2] * v[1],
+                u[2] * v[0] - u[0] * v[2],
+                u[0] * v[1] - u[1] * v[0],
+            ];
+            let l = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2])
+                .sqrt()
+                .max(f32::EPSILON);
+
+            writeln!(w, "  facet normal {} {} {}", n[0] / l, n[1] / l, n[2] / l)?;
+            writeln!(w, "    outer loop")?;
+            for p in [a, b, c] {
+                writeln!(w, "      vertex {} {} {}", p[0], p[1], p[2])?;
+            }
+            writeln!(w, "    endloop")?;
+            writeln!(w, "  endfacet")?;
+        }
+
+        writeln!(w, "endsolid bed_mesh")?;
+
+        Ok(())
+    }
+
+    /// Fetch points by row (increasing Y), returning an error if any are missing
+    fn rows(&self) -> Result<Vec<Vec<&Point>>, Error> {
+        let mut rows = Vec::with_capacity(self.ys.len());
+        let mut missing = 0;
+
+        for y in &self.ys {
+            let r: Vec<_> = self.xs.iter().filter_map(|x| self.value(*x, *y)).collect();
+            missing += self.xs.len() - r.len();
+            rows.push(r);
+        }
+
+        match missing {
+            0 => Ok(rows),
+            _ => Err(Error::IncompleteGrid {
+                missing,
+                nodes: self.xs.len() * self.ys.len(),
+            }),
+        }
+    }
+
+    /// Split grid cells into counter-clockwise (upward facing) triangles
+    fn triangles(&self) -> Vec<[&Point; 3]> {
+        let mut t = vec![];
+
+        for j in 1..self.ys.len() {
+            for i in 1..self.xs.len() {
+                let p = |i: usize, j: usize| self.value(self.xs[i], self.ys[j]);
+
+                let (p00, p10) = (p(i - 1, j - 1), p(i, j - 1));
+                let (p01, p11) = (p(i - 1, j), p(i, j));
+
+                if let (Some(a), Some(b), Some(c)) = (p00, p10, p11) {
+                    t.push([a, b, c]);
+                }
+                if let (Some(a), Some(b), Some(c)) = (p00, p11, p01) {
+                    t.push([a, b, c]);
+                }
+            }
+        }
+
+        t
+    }
+}
+
+/// Axis minimum and maximum
+fn bounds(a: &[f32]) -> (f32, f32) {
+    (
+        a.first().copied().unwrap_or_default(),
+        a.last().copied().unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    fn mesh() -> LevelMap {
+        LevelMap::new(vec![
+            Point::new(0.0, 0.0, 0.1, 0.0),
+            Point::new(100.0, 0.0, 0.2, 0.0),
+            Point::new(0.0, 50.0, 0.3, 0.0),
+            Point::new(100.0, 50.0, 0.4, 0.0),
+        ])
+    }
+
+    fn export(l: &LevelMap, format: MeshFormat) -> Result<String, Error> {
+        let mut b = vec![];
+        l.export(format, &mut b)?;
+        Ok(String::from_utf8(b).unwrap())
+    }
+
+    #[test]
+    fn export_csv() {
+        assert_eq!(
+            export(&mesh(), MeshFormat::Csv).unwrap(),
+            "x,y,z,d\n0,0,0.1,0\n100,0,0.2,0\n0,50,0.3,0\n100,50,0.4,0\n"
+        );
+    }
+
+    #[test]
+    fn export_json() {
+        let s = export(&mesh(), MeshFormat::Json).unwrap();
+        let l: LevelMap = serde_json::from_str(&s).unwrap();
+        assert_eq!(l, mesh());
+    }
+
+    #[test]
+    fn export_klipper() {
+        let s = export(&mesh(), MeshFormat::Klipper).unwrap();
+        assert!(s.starts_with("[bed_mesh default]\n"));
+        assert!(s.contains("points =\n\t0.100000, 0.200000\n\t0.300000, 0.400000\n"));
+        assert!(s.contains("x_count = 2\ny_count = 2\n"));
+        assert!(s.contains("min_x = 0\nmax_x = 100\nmin_y = 0\nmax_y = 50\n"));
+
+        // Klipper requires a full grid
+        let mut l = mesh();
+        l.points.pop();
+        assert!(matches!(
+            export(&l, MeshFormat::Klipper),
+            Err(Error::IncompleteGrid {
+                missing: 1,
+                nodes: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn export_marlin() {
+        let s = export(&mesh(), MeshFormat::Marlin).unwrap();
+        let lines: Vec<_> = s.lines().skip(1).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "M421 I0 J0 Z0.100",
+                "M421 I1 J0 Z0.200",
+                "M421 I0 J1 Z0.300",
+                "M421 I1 J1 Z0.400"
+            ]
+        );
+    }
+
+    #[test]
+    fn export_surfaces() {
+        let s = export(&mesh(), MeshFormat::Obj).unwrap();
+        assert_eq!(s.lines().filter(|l| l.starts_with("v ")).count(), 4);
+        assert_eq!(
+            s.lines()
+                .filter(|l| l.starts_with("f "))
+                .collect::<Vec<_>>(),
+            vec!["f 1 2 4", "f 1 4 3"]
+        );
+
+        let s = export(&mesh(), MeshFormat::Stl).unwrap();
+        assert_eq!(s.matches("facet normal").count(), 2);
+        assert!(s.ends_with("endsolid bed_mesh\n"));
+
+        // Cells with missing points are skipped
+        let mut l = mesh();
+        l.points.pop();
+        let s = export(&l, MeshFormat::Obj).unwrap();
+        assert_eq!(s.lines().filter(|l| l.starts_with("f ")).count(), 0);
+    }
+}
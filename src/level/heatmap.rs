@@ -0,0 +1,222 @@
+//! Coloured terminal heatmap rendering for [LevelMap]s
+
+use std::{
+    fmt::Display,
+    io::{stdout, IsTerminal},
+};
+
+use super::{LevelMap, Plane, Point};
+
+/// Heatmap metric
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, clap::ValueEnum, displaydoc::Display)]
+pub enum Metric {
+    /// z offset (mm)
+    #[default]
+    Offset,
+    /// z variance
+    Variance,
+    /// deviation from plane (mm)
+    Deviation,
+}
+
+/// Heatmap colour output
+#[derive(Clone, Copy, PartialEq, Eq, Debug, clap::ValueEnum)]
+pub enum ColorMode {
+    /// 24-bit colour
+    Truecolor,
+    /// 256 colour palette
+    Ansi,
+    /// No colour
+    Plain,
+}
+
+impl ColorMode {
+    /// Detect colour support for stdout from `NO_COLOR`, `COLORTERM` and `TERM`
+    pub fn detect() -> Self {
+        let env = |k: &str| std::env::var(k).unwrap_or_default();
+
+        if !stdout().is_terminal() || std::env::var_os("NO_COLOR").is_some() {
+            return Self::Plain;
+        }
+
+        match (env("COLORTERM").as_str(), env("TERM").as_str()) {
+            ("truecolor" | "24bit", _) => Self::Truecolor,
+            (_, "dumb" | "") => Self::Plain,
+            _ => Self::Ansi,
+        }
+    }
+}
+
+/// Heatmap renderer, drawn with the back of the bed (maximum Y) at the top
+pub struct Heatmap<'a> {
+    map: &'a LevelMap,
+    metric: Metric,
+    mode: ColorMode,
+    plane: Plane,
+}
+
+impl<'a> Heatmap<'a> {
+    /// Create a new heatmap for a mesh
+    pub fn new(map: &'a LevelMap, metric: Metric, mode: ColorMode) -> Self {
+        Self {
+            map,
+            metric,
+            mode,
+            plane: map.fit_plane(),
+        }
+    }
+
+    /// Metric value at a point
+    fn value(&self, p: &Point) -> f32 {
+        match self.metric {
+            Metric::Offset => p.c,
+            Metric::Variance => p.d,
+            Metric::Deviation => p.c - self.plane.at(p.x, p.y),
+        }
+    }
+
+    /// Colour scale range, symmetric about zero for deviation
+    fn range(&self) -> Option<(f32, f32)> {
+        let values = self.map.points.iter().map(|p| self.value(p));
+        let (min, max) = values.fold((f32::MAX, f32::MIN), |(a, b), v| (a.min(v), b.max(v)));
+
+        match (self.map.points.is_empty(), self.metric) {
+            (true, _) => None,
+            (false, Metric::Deviation) => {
+                let m = min.abs().max(max.abs());
+                Some((-m, m))
+            }
+            (false, _) => Some((min, max)),
+        }
+    }
+
+    /// Write a cell with a background colour for `t` in 0..=1
+    fn cell(&self, f: &mut std::fmt::Formatter<'_>, text: &str, t: f32) -> std::fmt::Result {
+        let [r, g, b] = gradient(t);
+
+        match self.mode {
+            ColorMode::Truecolor => write!(f, "\x1b[30;48;2;{r};{g};{b}m{text}\x1b[0m"),
+            ColorMode::Ansi => write!(f, "\x1b[30;48;5;{}m{text}\x1b[0m", ansi256(r, g, b)),
+            ColorMode::Plain => write!(f, "{text}"),
+        }
+    }
+}
+
+impl<'a> Display for Heatmap<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some((min, max)) = self.range() else {
+            return writeln!(f, "empty mesh");
+        };
+        let scale = |v: f32| match max - min {
+            s if s > f32::EPSILON => (v - min) / s,
+            _ => 0.5,
+        };
+
+        // Write X indices
+        write!(f, "        ")?;
+        for x in &self.map.xs {
+            write!(f, "{x:<8.01}")?;
+        }
+        writeln!(f)?;
+
+        // Write rows from the back of the bed
+        for y in self.map.ys.iter().rev() {
+            write!(f, "{y:>5.01}: ")?;
+
+            for x in &self.map.xs {
+                match self.map.value(*x, *y) {
+                    Some(p) => {
+                        let v = self.value(p);
+                        self.cell(f, &format!(" {v:>6.03} "), scale(v))?;
+                    }
+                    None => write!(f, "   --   ")?,
+                }
+            }
+
+            writeln!(f)?;
+        }
+
+        // Write legend
+        write!(f, "{}: {min:.03} ", self.metric)?;
+        if self.mode != ColorMode::Plain {
+            for i in 0..=16 {
+                self.cell(f, " ", i as f32 / 16.0)?;
+            }
+            write!(f, " ")?;
+        } else {
+            write!(f, "to ")?;
+        }
+        writeln!(f, "{max:.03}")
+    }
+}
+
+/// Blue (low) to white to red (high) colour gradient
+fn gradient(t: f32) -> [u8; 3] {
+    let t = t.clamp(0.0, 1.0);
+    let lerp = |a: f32, b: f32, t: f32| (a + (b - a) * t).round() as u8;
+
+    match t < 0.5 {
+        true => {
+            let t = t * 2.0;
+            [lerp(40.0, 255.0, t), lerp(90.0, 255.0, t), 255]
+        }
+        false => {
+            let t = (t - 0.5) * 2.0;
+            [255, lerp(255.0, 60.0, t), lerp(255.0, 40.0, t)]
+        }
+    }
+}
+
+/// Map RGB to the 6x6x6 colour cube of the 256 colour palette
+fn ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let c = |v: u8| (v as u16 * 5 / 255) as u8;
+    16 + 36 * c(r) + 6 * c(g) + c(b)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mesh() -> LevelMap {
+        LevelMap::new(vec![
+            Point::new(0.0, 0.0, 0.1, 0.01),
+            Point::new(100.0, 0.0, 0.3, 0.02),
+            Point::new(0.0, 100.0, 0.2, 0.01),
+        ])
+    }
+
+    #[test]
+    fn render_plain() {
+        let s = Heatmap::new(&mesh(), Metric::Offset, ColorMode::Plain).to_string();
+
+        assert_eq!(
+            s,
+            "        0.0     100.0   \n\
+             100.0:   0.200    --   \n\
+             \x20 0.0:   0.100   0.300 \n\
+             z offset (mm): 0.100 to 0.300\n"
+        );
+    }
+
+    #[test]
+    fn render_colour() {
+        let l = mesh();
+
+        let s = Heatmap::new(&l, Metric::Variance, ColorMode::Truecolor).to_string();
+        assert!(s.contains("\x1b[30;48;2;40;90;255m  0.010 \x1b[0m"));
+        assert!(s.contains("\x1b[30;48;2;255;60;40m  0.020 \x1b[0m"));
+
+        let s = Heatmap::new(&l, Metric::Deviation, ColorMode::Ansi).to_string();
+        assert!(s.contains("\x1b[30;48;5;231m  0.000 \x1b[0m"));
+        assert!(s.starts_with("        0.0"));
+    }
+
+    #[test]
+    fn colour_scale() {
+        assert_eq!(gradient(0.0), [40, 90, 255]);
+        assert_eq!(gradient(0.5), [255, 255, 255]);
+        assert_eq!(gradient(1.0), [255, 60, 40]);
+        assert_eq!(ansi256(255, 255, 255), 231);
+        assert_eq!(ansi256(0, 0, 0), 16);
+    }
+}
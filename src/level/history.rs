@@ -0,0 +1,316 @@
+//! Mesh history, storing levelling runs by printer serial for drift analysis
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::{bed_size, LevelMap, MeshStats};
+use crate::Error;
+
+/// Stored levelling mesh
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct MeshRecord {
+    /// Printer serial
+    pub serial: String,
+    /// Capture time
+    pub timestamp: DateTime<Utc>,
+    /// Measured mesh
+    pub map: LevelMap,
+}
+
+/// Mesh history store, a directory of JSON files per printer serial
+///
+/// Records are stored as `<root>/<serial>/<timestamp>.json`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct MeshStore {
+    root: PathBuf,
+}
+
+impl MeshStore {
+    /// Open a store, creating the root directory if required
+    pub fn open(root: impl AsRef<Path>) -> Result<Self, Error> {
+        let root = root.as_ref().to_path_buf();
+        std::fs::create_dir_all(&root)?;
+
+        Ok(Self { root })
+    }
+
+    /// Save a mesh record, returning the record path
+    ///
+    /// Existing records are not overwritten, saving a second record with the same
+    /// serial and timestamp fails.
+    pub fn save(&self, r: &MeshRecord) -> Result<PathBuf, Error> {
+        let dir = self.dir(&r.serial)?;
+        std::fs::create_dir_all(&dir)?;
+
+        let path = dir.join(format!("{}.json", r.timestamp.format("%Y%m%dT%H%M%S%.3fZ")));
+        let f = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        serde_json::to_writer_pretty(f, r)?;
+
+        Ok(path)
+    }
+
+    /// List serials with stored meshes
+    pub fn serials(&self) -> Result<Vec<String>, Error> {
+        let mut serials = vec![];
+
+        for e in std::fs::read_dir(&self.root)? {
+            let e = e?;
+            if e.file_type()?.is_dir() {
+                serials.extend(e.file_name().to_str().map(String::from));
+            }
+        }
+
+        serials.sort();
+        Ok(serials)
+    }
+
+    /// Load stored meshes for a printer, ordered by capture time
+    ///
+    /// Records that fail to load are skipped with a warning.
+    pub fn list(&self, serial: &str) -> Result<Vec<MeshRecord>, Error> {
+        let dir = self.dir(serial)?;
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut records = vec![];
+        for e in std::fs::read_dir(dir)? {
+            let path = e?.path();
+            if path.extension().map(|e| e != "json").unwrap_or(true) {
+                continue;
+            }
+
+            let r = std::fs::File::open(&path)
+                .map_err(Error::from)
+                .and_then(|f| Ok(serde_json::from_reader::<_, MeshRecord>(f)?));
+            match r {
+                Ok(r) => records.push(r),
+                Err(e) => warn!("Skipping mesh record {}: {e}", path.display()),
+            }
+        }
+
+        records.sort_by_key(|r| r.timestamp);
+        Ok(records)
+    }
+
+    fn dir(&self, serial: &str) -> Result<PathBuf, Error> {
+        let valid = !serial.is_empty()
+            && serial
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+        match valid {
+            true => Ok(self.root.join(serial)),
+            false => Err(Error::InvalidSerial(serial.to_string())),
+        }
+    }
+}
+
+/// Mesh trend entry
+#[derive(Clone, PartialEq, Debug)]
+pub struct TrendEntry {
+    /// Capture time
+    pub timestamp: DateTime<Utc>,
+    /// Mesh statistics
+    pub stats: MeshStats,
+    /// Maximum absolute change from the previous mesh (mm)
+    pub change: Option<f32>,
+    /// Maximum absolute change from the first mesh (mm)
+    pub drift: Option<f32>,
+}
+
+/// Mesh trend report
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Trend {
+    pub entries: Vec<TrendEntry>,
+    /// Range change per week (mm)
+    pub range_rate: Option<f32>,
+    /// X tilt change per week (mm)
+    pub tilt_x_rate: Option<f32>,
+    /// Y tilt change per week (mm)
+    pub tilt_y_rate: Option<f32>,
+    /// Flatness change per week (mm)
+    pub flatness_rate: Option<f32>,
+}
+
+/// Build a trend report from mesh records ordered by capture time
+///
+/// Rates are least-squares slopes over time, `None` with fewer than two entries.
+pub fn trend(records: &[MeshRecord]) -> Trend {
+    let mut entries = vec![];
+
+    for (i, r) in records.iter().enumerate() {
+        let Some(stats) = r.map.stats(bed_size(Some(&r.serial))) else {
+            continue;
+        };
+
+        let change = i
+            .checked_sub(1)
+            .and_then(|p| r.map.diff(&records[p].map).max_abs());
+        let drift = match i {
+            0 => None,
+            _ => r.map.diff(&records[0].map).max_abs(),
+        };
+
+        entries.push(TrendEntry {
+            timestamp: r.timestamp,
+            stats,
+            change,
+            drift,
+        });
+    }
+
+    let rate = |f: fn(&MeshStats) -> f32| weekly_rate(&entries, f);
+
+    Trend {
+        range_rate: rate(|s| s.range),
+        tilt_x_rate: rate(|s| s.tilt_x),
+        tilt_y_rate: rate(|s| s.tilt_y),
+        flatness_rate: rate(|s| s.flatness),
+        entries,
+    }
+}
+
+/// Least-squares slope of a statistic per week
+fn weekly_rate(entries: &[TrendEntry], f: fn(&MeshStats) -> f32) -> Option<f32> {
+    let t0 = entries.first()?.timestamp;
+    let v: Vec<_> = entries
+        .iter()
+        .map(|e| {
+            let weeks = (e.timestamp - t0).num_seconds() as f64 / (7.0 * 24.0 * 3600.0);
+            (weeks, f(&e.stats) as f64)
+        })
+        .collect();
+
+    let n = v.len() as f64;
+    let mt = v.iter().map(|(t, _)| t).sum::<f64>() / n;
+    let mv = v.iter().map(|(_, v)| v).sum::<f64>() / n;
+
+    let stt: f64 = v.iter().map(|(t, _)| (t - mt).powi(2)).sum();
+    let stv: f64 = v.iter().map(|(t, v)| (t - mt) * (v - mv)).sum();
+
+    match stt > f64::EPSILON {
+        true => Some((stv / stt) as f32),
+        false => None,
+    }
+}
+
+/// Display a [Trend] as a table
+impl Display for Trend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let opt = |v: Option<f32>| match v {
+            Some(v) => format!("{v:>7.03}"),
+            None => format!("{:>7}", "-"),
+        };
+
+        writeln!(
+            f,
+            "{:<17} {:>7} {:>7} {:>7} {:>7} {:>7} {:>7} {:>7}",
+            "time", "mean", "range", "tilt x", "tilt y", "flat", "change", "drift"
+        )?;
+
+        for e in &self.entries {
+            let s = &e.stats;
+            writeln!(
+                f,
+                "{:<17} {:>7.03} {:>7.03} {:>+7.03} {:>+7.03} {:>7.03} {} {}",
+                e.timestamp.format("%Y-%m-%d %H:%M"),
+                s.mean,
+                s.range,
+                s.tilt_x,
+                s.tilt_y,
+                s.flatness,
+                opt(e.change),
+                opt(e.drift)
+            )?;
+        }
+
+        write!(
+            f,
+            "{:<17} {:>7} {} {} {} {}",
+            "per week",
+            "",
+            opt(self.range_rate),
+            opt(self.tilt_x_rate),
+            opt(self.tilt_y_rate),
+            opt(self.flatness_rate)
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::level::Point;
+
+    fn record(days: i64, z: f32) -> MeshRecord {
+        let mut points = vec![];
+        for y in [0.0, 100.0] {
+            for x in [0.0, 100.0] {
+                // Corner rising by `z`
+                let c = match (x, y) {
+                    (100.0, 100.0) => z,
+                    _ => 0.0,
+                };
+                points.push(Point::new(x, y, c, 0.01));
+            }
+        }
+
+        MeshRecord {
+            serial: "01P00A000000000".to_string(),
+            timestamp: DateTime::from_timestamp(1_700_000_000 + days * 24 * 3600, 0).unwrap(),
+            map: LevelMap::new(points),
+        }
+    }
+
+    #[test]
+    fn store_roundtrip() {
+        let root = std::env::temp_dir().join(format!("bambu-mesh-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let s = MeshStore::open(&root).unwrap();
+
+        let records = vec![record(7, 0.2), record(0, 0.1)];
+        for r in &records {
+            s.save(r).unwrap();
+        }
+
+        // Records with the same timestamp are not overwritten
+        assert!(matches!(s.save(&record(7, 0.5)), Err(Error::Io(_))));
+
+        assert_eq!(s.serials().unwrap(), vec!["01P00A000000000".to_string()]);
+        assert_eq!(
+            s.list("01P00A000000000").unwrap(),
+            vec![records[1].clone(), records[0].clone()]
+        );
+        assert_eq!(s.list("unknown").unwrap(), vec![]);
+        assert!(matches!(s.list("../etc"), Err(Error::InvalidSerial(_))));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn drift() {
+        let t = trend(&[record(0, 0.1), record(7, 0.2), record(14, 0.4)]);
+
+        let change: Vec<_> = t.entries.iter().map(|e| e.change).collect();
+        let drift: Vec<_> = t.entries.iter().map(|e| e.drift).collect();
+        assert_eq!(change[0], None);
+        assert_eq!(drift[0], None);
+        assert!((change[2].unwrap() - 0.2).abs() < 1e-5);
+        assert!((drift[2].unwrap() - 0.3).abs() < 1e-5);
+
+        // Range grows 0.15mm per week
+        assert!((t.range_rate.unwrap() - 0.15).abs() < 1e-4);
+        assert_eq!(trend(&[record(0, 0.1)]).range_rate, None);
+    }
+}
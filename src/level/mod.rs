@@ -0,0 +1,721 @@
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{model::Model, Error};
+
+mod export;
+pub use export::*;
+
+mod segment;
+pub use segment::*;
+
+mod history;
+pub use history::*;
+
+mod screws;
+pub use screws::*;
+
+mod outliers;
+pub use outliers::*;
+
+mod heatmap;
+pub use heatmap::*;
+
+/// Bed size used for tilt when the printer model is unknown (mm)
+pub const DEFAULT_BED_SIZE: (f32, f32) = (256.0, 256.0);
+
+/// Bed size for a printer serial, falling back to [DEFAULT_BED_SIZE] for unknown models
+pub fn bed_size(serial: Option<&str>) -> (f32, f32) {
+    serial
+        .and_then(Model::from_serial)
+        .map(|m| m.bed_size())
+        .unwrap_or(DEFAULT_BED_SIZE)
+}
+
+/// Bed levelling point
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Point {
+    /// X location
+    pub x: f32,
+    /// Y location
+    pub y: f32,
+    /// z offset
+    pub c: f32,
+    /// z variance
+    pub d: f32,
+}
+
+impl Point {
+    /// Create a new point
+    pub fn new(x: f32, y: f32, c: f32, d: f32) -> Self {
+        Self { x, y, c, d }
+    }
+}
+
+/// Interpolation method for sampling a [LevelMap] between points
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, clap::ValueEnum)]
+pub enum Interpolation {
+    /// Linear interpolation between the four surrounding points
+    #[default]
+    Bilinear,
+    /// Cubic (Catmull-Rom) interpolation over the sixteen surrounding points
+    Bicubic,
+}
+
+/// Bed levelling map
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct LevelMap {
+    pub xs: Vec<f32>,
+    pub ys: Vec<f32>,
+    pub points: Vec<Point>,
+}
+
+/// Default tolerance for grouping probe coordinates into grid axes (mm)
+pub const GRID_TOLERANCE: f32 = 1.0;
+
+/// Grid construction report
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct GridReport {
+    /// Grid nodes with no measurement
+    pub missing: Vec<(f32, f32)>,
+    /// Measurements replaced by a later measurement of the same node
+    pub duplicates: Vec<Point>,
+}
+
+impl GridReport {
+    /// Check whether the measurements form a full rectangular grid
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// Minimum variance used when weighting points, avoids infinite weights for `d = 0`
+pub const MIN_VARIANCE: f32 = 1e-4;
+
+/// Least-squares plane fit, `z = a * x + b * y + c`
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Plane {
+    /// X slope (mm/mm)
+    pub a: f32,
+    /// Y slope (mm/mm)
+    pub b: f32,
+    /// Offset (mm)
+    pub c: f32,
+}
+
+impl Plane {
+    /// Evaluate the plane at a location
+    pub fn at(&self, x: f32, y: f32) -> f32 {
+        self.a * x + self.b * y + self.c
+    }
+}
+
+/// Bed mesh statistics
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MeshStats {
+    /// Minimum z offset (mm)
+    pub min: f32,
+    /// Maximum z offset (mm)
+    pub max: f32,
+    /// Range of z offsets (mm)
+    pub range: f32,
+    /// Mean z offset (mm)
+    pub mean: f32,
+    /// Standard deviation of z offsets (mm)
+    pub std_dev: f32,
+    /// Variance weighted plane fit
+    pub plane: Plane,
+    /// Tilt across the bed width in X (mm)
+    pub tilt_x: f32,
+    /// Tilt across the bed depth in Y (mm)
+    pub tilt_y: f32,
+    /// Range of residuals after removing the fitted plane (mm)
+    pub flatness: f32,
+}
+
+impl Display for MeshStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "min: {:.03} max: {:.03} range: {:.03} mean: {:.03} std: {:.03}",
+            self.min, self.max, self.range, self.mean, self.std_dev
+        )?;
+        write!(
+            f,
+            "tilt x: {:+.03} y: {:+.03} flatness: {:.03}",
+            self.tilt_x, self.tilt_y, self.flatness
+        )
+    }
+}
+
+impl LevelMap {
+    /// Create a new LevelMap from a list of levelling points
+    ///
+    /// Coordinates are snapped to grid axes within [GRID_TOLERANCE], see [LevelMap::with_tolerance].
+    pub fn new(points: Vec<Point>) -> Self {
+        Self::with_tolerance(points, GRID_TOLERANCE).0
+    }
+
+    /// Create a new LevelMap, returning an error if the points do not form a full rectangular grid
+    pub fn try_new(points: Vec<Point>) -> Result<Self, Error> {
+        let (l, r) = Self::with_tolerance(points, GRID_TOLERANCE);
+
+        match r.is_complete() {
+            true => Ok(l),
+            false => Err(Error::IncompleteGrid {
+                missing: r.missing.len(),
+                nodes: l.xs.len() * l.ys.len(),
+            }),
+        }
+    }
+
+    /// Create a new LevelMap, snapping coordinates within `tolerance` (mm) to sorted grid axes
+    ///
+    /// Later measurements of the same grid node replace earlier ones, with missing and
+    /// duplicate measurements listed in the returned [GridReport].
+    pub fn with_tolerance(points: Vec<Point>, tolerance: f32) -> (Self, GridReport) {
+        let xs = cluster(points.iter().map(|p| p.x), tolerance);
+        let ys = cluster(points.iter().map(|p| p.y), tolerance);
+
+        let mut report = GridReport::default();
+        let mut nodes: Vec<Option<Point>> = vec![None; xs.len() * ys.len()];
+
+        for mut p in points {
+            let (i, j) = (nearest(&xs, p.x), nearest(&ys, p.y));
+            p.x = xs[i];
+            p.y = ys[j];
+
+            if let Some(d) = nodes[j * xs.len() + i].replace(p) {
+                report.duplicates.push(d);
+            }
+        }
+
+        let mut points = Vec::with_capacity(nodes.len());
+        for (n, p) in nodes.into_iter().enumerate() {
+            match p {
+                Some(p) => points.push(p),
+                None => report.missing.push((xs[n % xs.len()], ys[n / xs.len()])),
+            }
+        }
+
+        (Self { xs, ys, points }, report)
+    }
+
+    /// Fetch the measured point at an exact location
+    pub fn value(&self, x: f32, y: f32) -> Option<&Point> {
+        self.points.iter().find(|v| v.x == x && v.y == y)
+    }
+
+    /// Sample the z offset at an arbitrary location within the mesh
+    pub fn interpolate(&self, x: f32, y: f32, method: Interpolation) -> Option<f32> {
+        self.sample(x, y, method, |p| p.c)
+    }
+
+    /// Build regular axes spanning the mesh with `nx` by `ny` points
+    pub fn grid(&self, nx: usize, ny: usize) -> (Vec<f32>, Vec<f32>) {
+        let axis = |a: &[f32], n: usize| -> Vec<f32> {
+            let (min, max) = match (a.first(), a.last()) {
+                (Some(min), Some(max)) => (*min, *max),
+                _ => return vec![],
+            };
+
+            match n {
+                0 => vec![],
+                1 => vec![(min + max) / 2.0],
+                _ => (0..n)
+                    .map(|i| min + (max - min) * i as f32 / (n - 1) as f32)
+                    .collect(),
+            }
+        };
+
+        (axis(&self.xs, nx), axis(&self.ys, ny))
+    }
+
+    /// Resample the mesh to a regular grid of `nx` by `ny` points
+    pub fn resample(&self, nx: usize, ny: usize, method: Interpolation) -> LevelMap {
+        let (xs, ys) = self.grid(nx, ny);
+
+        let mut points = Vec::with_capacity(xs.len() * ys.len());
+        for y in &ys {
+            for x in &xs {
+                if let (Some(c), Some(d)) = (
+                    self.sample(*x, *y, method, |p| p.c),
+                    self.sample(*x, *y, method, |p| p.d),
+                ) {
+                    points.push(Point::new(*x, *y, c, d));
+                }
+            }
+        }
+
+        LevelMap { xs, ys, points }
+    }
+
+    /// Compute mesh statistics, returning `None` for an empty mesh
+    ///
+    /// The plane fit weights each point by the inverse of its variance (`d`),
+    /// with tilt reported as the plane height difference across the bed `(width, depth)`,
+    /// see [bed_size].
+    pub fn stats(&self, bed: (f32, f32)) -> Option<MeshStats> {
+        if self.points.is_empty() {
+            return None;
+        }
+
+        let n = self.points.len() as f32;
+        let (min, max) = self
+            .points
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), p| {
+                (min.min(p.c), max.max(p.c))
+            });
+        let mean = self.points.iter().map(|p| p.c).sum::<f32>() / n;
+        let var = self
+            .points
+            .iter()
+            .map(|p| (p.c - mean).powi(2))
+            .sum::<f32>()
+            / n;
+
+        let plane = self.fit_plane();
+        let (rmin, rmax) = self
+            .points
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), p| {
+                let r = p.c - plane.at(p.x, p.y);
+                (min.min(r), max.max(r))
+            });
+
+        Some(MeshStats {
+            min,
+            max,
+            range: max - min,
+            mean,
+            std_dev: var.sqrt(),
+            plane,
+            tilt_x: plane.a * bed.0,
+            tilt_y: plane.b * bed.1,
+            flatness: rmax - rmin,
+        })
+    }
+
+    /// Compute the per-point change from `other` to this mesh
+    ///
+    /// `other` is sampled at each point in this mesh, points outside `other` are omitted.
+    /// Variances (`d`) are summed.
+    pub fn diff(&self, other: &LevelMap) -> LevelMap {
+        let method = Interpolation::Bilinear;
+
+        let points = self
+            .points
+            .iter()
+            .filter_map(|p| {
+                let c = other.sample(p.x, p.y, method, |p| p.c)?;
+                let d = other.sample(p.x, p.y, method, |p| p.d)?;
+                Some(Point::new(p.x, p.y, p.c - c, p.d + d))
+            })
+            .collect();
+
+        LevelMap {
+            xs: self.xs.clone(),
+            ys: self.ys.clone(),
+            points,
+        }
+    }
+
+    /// Maximum absolute z offset, for use with [LevelMap::diff]
+    pub fn max_abs(&self) -> Option<f32> {
+        self.points.iter().map(|p| p.c.abs()).max_by(f32::total_cmp)
+    }
+
+    /// Weighted least-squares plane fit, slopes are zero for axes with no extent
+    fn fit_plane(&self) -> Plane {
+        let w = |p: &Point| 1.0 / p.d.abs().max(MIN_VARIANCE) as f64;
+
+        // Weighted centroid
+        let sw: f64 = self.points.iter().map(w).sum();
+        let wm = |f: fn(&Point) -> f32| -> f64 {
+            self.points.iter().map(|p| w(p) * f(p) as f64).sum::<f64>() / sw
+        };
+        let (mx, my, mz) = (wm(|p| p.x), wm(|p| p.y), wm(|p| p.c));
+
+        // Centred sums for the normal equations
+        let (mut sxx, mut sxy, mut syy, mut sxz, mut syz) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for p in &self.points {
+            let (x, y, z) = (p.x as f64 - mx, p.y as f64 - my, p.c as f64 - mz);
+            let w = w(p);
+            sxx += w * x * x;
+            sxy += w * x * y;
+            syy += w * y * y;
+            sxz += w * x * z;
+            syz += w * y * z;
+        }
+
+        let det = sxx * syy - sxy * sxy;
+        let (a, b) = match det.abs() > f64::EPSILON * (sxx * syy).max(1.0) {
+            true => ((sxz * syy - syz * sxy) / det, (syz * sxx - sxz * sxy) / det),
+            // Degenerate (single row or column), fit each axis independently
+            false => {
+                let slope = |s: f64, sz: f64| if s > f64::EPSILON { sz / s } else { 0.0 };
+                (slope(sxx, sxz), slope(syy, syz))
+            }
+        };
+
+        Plane {
+            a: a as f32,
+            b: b as f32,
+            c: (mz - a * mx - b * my) as f32,
+        }
+    }
+
+    /// Sample a point field at an arbitrary location within the mesh
+    fn sample(
+        &self,
+        x: f32,
+        y: f32,
+        method: Interpolation,
+        f: impl Fn(&Point) -> f32,
+    ) -> Option<f32> {
+        let (i, tx) = locate(&self.xs, x)?;
+        let (j, ty) = locate(&self.ys, y)?;
+
+        // Fetch node values by offset from the cell, clamped to the mesh edges
+        let node = |di: isize, dj: isize| {
+            let i = (i as isize + di).clamp(0, self.xs.len() as isize - 1) as usize;
+            let j = (j as isize + dj).clamp(0, self.ys.len() as isize - 1) as usize;
+            self.value(self.xs[i], self.ys[j]).map(&f)
+        };
+
+        let bilinear = || {
+            let (z00, z10) = (node(0, 0)?, node(1, 0)?);
+            let (z01, z11) = (node(0, 1)?, node(1, 1)?);
+
+            Some(lerp(lerp(z00, z10, tx), lerp(z01, z11, tx), ty))
+        };
+
+        match method {
+            Interpolation::Bilinear => bilinear(),
+            Interpolation::Bicubic => {
+                let (i, j) = (i as isize, j as isize);
+                let (nx, ny) = (self.xs.len() as isize, self.ys.len() as isize);
+                let at = |i: isize, j: isize| {
+                    self.value(self.xs[i as usize], self.ys[j as usize]).map(&f)
+                };
+
+                let row = |j: isize| {
+                    let g = |i| at(i, j);
+                    let p = [
+                        extend(nx, i - 1, g)?,
+                        extend(nx, i, g)?,
+                        extend(nx, i + 1, g)?,
+                        extend(nx, i + 2, g)?,
+                    ];
+                    Some(cubic(p, tx))
+                };
+
+                let rows = [
+                    extend(ny, j - 1, row),
+                    extend(ny, j, row),
+                    extend(ny, j + 1, row),
+                    extend(ny, j + 2, row),
+                ];
+
+                match rows {
+                    [Some(a), Some(b), Some(c), Some(d)] => Some(cubic([a, b, c, d], ty)),
+                    // Fallback to bilinear where neighbouring points are missing
+                    _ => bilinear(),
+                }
+            }
+        }
+    }
+}
+
+/// Fetch a value by axis index, linearly extrapolating beyond the mesh edges
+fn extend(n: isize, k: isize, g: impl Fn(isize) -> Option<f32>) -> Option<f32> {
+    match k {
+        _ if n < 2 => g(k.clamp(0, n - 1)),
+        k if k < 0 => Some(2.0 * g(0)? - g(1)?),
+        k if k >= n => Some(2.0 * g(n - 1)? - g(n - 2)?),
+        k => g(k),
+    }
+}
+
+/// Group sorted values within `tolerance` of the first value in each group, returning group means
+fn cluster(values: impl Iterator<Item = f32>, tolerance: f32) -> Vec<f32> {
+    let mut values: Vec<_> = values.collect();
+    values.sort_by(f32::total_cmp);
+
+    let mut axis = vec![];
+    let mut group: Vec<f32> = vec![];
+
+    for v in values {
+        if let Some(first) = group.first() {
+            if v - first > tolerance {
+                axis.push(group.iter().sum::<f32>() / group.len() as f32);
+                group.clear();
+            }
+        }
+        group.push(v);
+    }
+
+    if !group.is_empty() {
+        axis.push(group.iter().sum::<f32>() / group.len() as f32);
+    }
+
+    axis
+}
+
+/// Find the index of the nearest axis value
+fn nearest(axis: &[f32], v: f32) -> usize {
+    axis.iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| (*a - v).abs().total_cmp(&(*b - v).abs()))
+        .map(|(i, _)| i)
+        .unwrap_or_default()
+}
+
+/// Locate the axis cell containing a value, returning the lower index and offset within the cell
+fn locate(axis: &[f32], v: f32) -> Option<(usize, f32)> {
+    let (first, last) = (*axis.first()?, *axis.last()?);
+    if v < first || v > last {
+        return None;
+    }
+
+    if axis.len() == 1 {
+        return Some((0, 0.0));
+    }
+
+    let i = axis
+        .windows(2)
+        .position(|w| v <= w[1])
+        .unwrap_or(axis.len() - 2);
+    let t = (v - axis[i]) / (axis[i + 1] - axis[i]);
+
+    Some((i, t))
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Catmull-Rom interpolation between `p[1]` and `p[2]`
+fn cubic(p: [f32; 4], t: f32) -> f32 {
+    p[1] + 0.5
+        * t
+        * (p[2] - p[0]
+            + t * (2.0 * p[0] - 5.0 * p[1] + 4.0 * p[2] - p[3]
+                + t * (3.0 * (p[1] - p[2]) + p[3] - p[0])))
+}
+
+/// Display a [LevelMap] in the terminal
+impl Display for LevelMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Write X indicies
+        write!(f, "        ")?;
+        for x in &self.xs {
+            write!(f, "{x:<7.01}")?;
+        }
+        writeln!(f)?;
+
+        // Write points line by line
+        for y in &self.ys {
+            write!(f, "{y:>5.01}: ")?;
+
+            for x in &self.xs {
+                let v = self.value(*x, *y);
+
+                match v {
+                    Some(v) => write!(f, "{:>6.03} ", v.c)?,
+                    None => write!(f, "????? ")?,
+                }
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// 3x3 mesh with z = x / 100 + y / 200
+    fn plane() -> LevelMap {
+        let mut points = vec![];
+        for y in [0.0, 100.0, 200.0] {
+            for x in [200.0, 0.0, 100.0] {
+                points.push(Point::new(x, y, x / 100.0 + y / 200.0, 0.01));
+            }
+        }
+        LevelMap::new(points)
+    }
+
+    #[test]
+    fn interpolate() {
+        let l = plane();
+        assert_eq!(l.xs, vec![0.0, 100.0, 200.0]);
+
+        for m in [Interpolation::Bilinear, Interpolation::Bicubic] {
+            // Exact points
+            assert_eq!(l.interpolate(100.0, 200.0, m), Some(2.0));
+            assert_eq!(l.interpolate(200.0, 0.0, m), Some(2.0));
+
+            // Between points, exact for a plane
+            let v = l.interpolate(50.0, 150.0, m).unwrap();
+            assert!((v - 1.25).abs() < 1e-6, "{m:?}: {v}");
+
+            // Outside the mesh
+            assert_eq!(l.interpolate(-1.0, 0.0, m), None);
+            assert_eq!(l.interpolate(0.0, 201.0, m), None);
+        }
+    }
+
+    #[test]
+    fn bicubic_curve() {
+        // z = (x / 100)^2, bicubic is closer than bilinear
+        let points = (0..5)
+            .flat_map(|y| (0..5).map(move |x| (x as f32 * 100.0, y as f32 * 100.0)))
+            .map(|(x, y)| Point::new(x, y, (x / 100.0).powi(2), 0.0))
+            .collect();
+        let l = LevelMap::new(points);
+
+        let expected = 1.5f32.powi(2);
+        let linear = l
+            .interpolate(150.0, 150.0, Interpolation::Bilinear)
+            .unwrap();
+        let cubic = l.interpolate(150.0, 150.0, Interpolation::Bicubic).unwrap();
+
+        assert!((cubic - expected).abs() < (linear - expected).abs());
+    }
+
+    #[test]
+    fn resample() {
+        let r = plane().resample(5, 3, Interpolation::Bilinear);
+
+        assert_eq!(r.xs, vec![0.0, 50.0, 100.0, 150.0, 200.0]);
+        assert_eq!(r.ys, vec![0.0, 100.0, 200.0]);
+        assert_eq!(r.points.len(), 15);
+        assert_eq!(r.value(150.0, 100.0).map(|p| p.c), Some(2.0));
+        assert_eq!(r.value(150.0, 100.0).map(|p| p.d), Some(0.01));
+    }
+
+    #[test]
+    fn stats() {
+        let s = plane().stats((250.0, 300.0)).unwrap();
+
+        assert_eq!(s.min, 0.0);
+        assert_eq!(s.max, 3.0);
+        assert_eq!(s.range, 3.0);
+        assert!((s.mean - 1.5).abs() < 1e-5);
+        assert!((s.plane.a - 0.01).abs() < 1e-5);
+        assert!((s.plane.b - 0.005).abs() < 1e-5);
+        assert!(s.plane.c.abs() < 1e-5);
+        // Tilt over the bed rather than the 200mm mesh span
+        assert!((s.tilt_x - 2.5).abs() < 1e-4);
+        assert!((s.tilt_y - 1.5).abs() < 1e-4);
+        assert!(s.flatness < 1e-4);
+
+        assert!(LevelMap::new(vec![]).stats(DEFAULT_BED_SIZE).is_none());
+
+        assert_eq!(bed_size(Some("03000A000000000")), (180.0, 180.0));
+        assert_eq!(bed_size(None), DEFAULT_BED_SIZE);
+    }
+
+    #[test]
+    fn weighted_fit() {
+        // Flat bed with a single noisy high point
+        let mut points = vec![];
+        for y in [0.0, 100.0, 200.0] {
+            for x in [0.0, 100.0, 200.0] {
+                points.push(Point::new(x, y, 0.0, 0.001));
+            }
+        }
+        points[8] = Point::new(200.0, 200.0, 1.0, 10.0);
+
+        let s = LevelMap::new(points.clone())
+            .stats(DEFAULT_BED_SIZE)
+            .unwrap();
+        assert!(s.tilt_x.abs() < 1e-3);
+        assert!(s.tilt_y.abs() < 1e-3);
+        assert!((s.flatness - 1.0).abs() < 1e-3);
+
+        // With equal weights the outlier tilts the plane
+        points[8].d = 0.001;
+        let s = LevelMap::new(points).stats(DEFAULT_BED_SIZE).unwrap();
+        assert!(s.tilt_x > 0.1);
+        assert!(s.tilt_y > 0.1);
+    }
+
+    #[test]
+    fn diff() {
+        // Coarser reference mesh, offset by 0.1mm
+        let mut points = vec![];
+        for y in [0.0, 200.0] {
+            for x in [0.0, 200.0] {
+                points.push(Point::new(x, y, x / 100.0 + y / 200.0 - 0.1, 0.01));
+            }
+        }
+        let other = LevelMap::new(points);
+
+        let d = plane().diff(&other);
+
+        assert_eq!(d.points.len(), 9);
+        assert!(d.points.iter().all(|p| (p.c - 0.1).abs() < 1e-5));
+        assert!(d.points.iter().all(|p| (p.d - 0.02).abs() < 1e-5));
+        assert!((d.max_abs().unwrap() - 0.1).abs() < 1e-5);
+
+        // Points outside the reference mesh are omitted
+        let mut other = other;
+        other.points.retain(|p| p.x < 100.0);
+        other.xs.retain(|x| *x < 100.0);
+        let d = plane().diff(&other);
+        assert_eq!(d.points.len(), 3);
+        assert!(d.points.iter().all(|p| p.x == 0.0));
+    }
+
+    #[test]
+    fn snap_grid() {
+        let points = vec![
+            Point::new(100.02, 0.0, 0.2, 0.0),
+            Point::new(0.0, 0.01, 0.1, 0.0),
+            Point::new(99.98, 99.97, 0.4, 0.0),
+            Point::new(0.01, 100.0, 0.3, 0.0),
+            // Repeated measurement
+            Point::new(100.0, 100.0, 0.5, 0.0),
+        ];
+
+        let (l, r) = LevelMap::with_tolerance(points, 0.5);
+
+        assert_eq!(l.xs, vec![0.005, 100.0]);
+        assert_eq!(l.ys, vec![0.005, 99.99]);
+        assert_eq!(l.value(100.0, 99.99).map(|p| p.c), Some(0.5));
+        assert_eq!(r.duplicates.len(), 1);
+        assert_eq!(r.duplicates[0].c, 0.4);
+        assert!(r.is_complete());
+    }
+
+    #[test]
+    fn incomplete_grid() {
+        let points = vec![
+            Point::new(0.0, 0.0, 0.1, 0.0),
+            Point::new(100.0, 0.0, 0.2, 0.0),
+            Point::new(0.0, 100.0, 0.3, 0.0),
+        ];
+
+        let (_l, r) = LevelMap::with_tolerance(points.clone(), GRID_TOLERANCE);
+        assert_eq!(r.missing, vec![(100.0, 100.0)]);
+        assert!(!r.is_complete());
+
+        assert!(LevelMap::try_new(points).is_err());
+    }
+
+    #[test]
+    fn missing_points() {
+        let mut l = plane();
+        l.points.retain(|p| !(p.x == 200.0 && p.y == 200.0));
+
+        assert_eq!(l.interpolate(150.0, 150.0, Interpolation::Bilinear), None);
+        assert!(l.interpolate(50.0, 50.0, Interpolation::Bicubic).is_some());
+    }
+}
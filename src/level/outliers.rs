@@ -0,0 +1,266 @@
+//! Outlier and bad probe detection for levelling data
+
+use super::{LevelMap, Point, GRID_TOLERANCE, MIN_VARIANCE};
+
+/// Weight of a flagged measurement when re-weighting outliers
+pub const OUTLIER_WEIGHT: f32 = 0.1;
+
+/// Probe retry, reported in `prev_z_c_diff` lines
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ProbeRetry {
+    /// X location
+    pub x: f32,
+    /// Y location
+    pub y: f32,
+    /// Change from the previous measurement (mm)
+    pub diff: f32,
+}
+
+/// Outlier detection limits
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct OutlierLimits {
+    /// Maximum variance as a multiple of the median mesh variance
+    pub variance_factor: f32,
+    /// Maximum deviation from the median of neighbouring points (mm)
+    pub max_deviation: f32,
+    /// Maximum change between probe retries (mm)
+    pub max_retry_diff: f32,
+}
+
+impl Default for OutlierLimits {
+    fn default() -> Self {
+        Self {
+            variance_factor: 4.0,
+            max_deviation: 0.1,
+            max_retry_diff: 0.05,
+        }
+    }
+}
+
+/// Reason for flagging a point
+#[derive(Clone, Copy, PartialEq, Debug, displaydoc::Display)]
+pub enum OutlierReason {
+    /// variance {d:.03} exceeds {limit:.03}
+    HighVariance { d: f32, limit: f32 },
+    /// deviates {deviation:+.03}mm from neighbours
+    NeighbourDeviation { deviation: f32 },
+    /// changed {diff:+.03}mm on retry
+    RetryDisagreement { diff: f32 },
+}
+
+/// Flagged measurement
+#[derive(Clone, PartialEq, Debug)]
+pub struct Outlier {
+    pub point: Point,
+    /// Median of neighbouring points, if any
+    pub expected: Option<f32>,
+    pub reasons: Vec<OutlierReason>,
+}
+
+/// Handling for flagged measurements
+#[derive(Clone, Copy, PartialEq, Eq, Debug, clap::ValueEnum)]
+pub enum OutlierAction {
+    /// Remove flagged points from the mesh
+    Exclude,
+    /// Blend flagged points towards their neighbours and reduce their plane fit weight
+    Reweight,
+}
+
+impl LevelMap {
+    /// Detect outlier probes by variance, neighbour deviation and retry disagreement
+    pub fn outliers(&self, limits: &OutlierLimits, retries: &[ProbeRetry]) -> Vec<Outlier> {
+        let limit = median(self.points.iter().map(|p| p.d.abs()).collect())
+            .map(|m| m.max(MIN_VARIANCE) * limits.variance_factor);
+
+        let mut outliers = vec![];
+
+        for p in &self.points {
+            let mut reasons = vec![];
+
+            if let Some(limit) = limit {
+                if p.d.abs() > limit {
+                    reasons.push(OutlierReason::HighVariance { d: p.d, limit });
+                }
+            }
+
+            let expected = self.neighbour_median(p);
+            if let Some(e) = expected {
+                let deviation = p.c - e;
+                if deviation.abs() > limits.max_deviation {
+                    reasons.push(OutlierReason::NeighbourDeviation { deviation });
+                }
+            }
+
+            let retry = retries
+                .iter()
+                .filter(|r| {
+                    (r.x - p.x).abs() <= GRID_TOLERANCE && (r.y - p.y).abs() <= GRID_TOLERANCE
+                })
+                .map(|r| r.diff)
+                .max_by(|a, b| a.abs().total_cmp(&b.abs()));
+            if let Some(diff) = retry {
+                if diff.abs() > limits.max_retry_diff {
+                    reasons.push(OutlierReason::RetryDisagreement { diff });
+                }
+            }
+
+            if !reasons.is_empty() {
+                outliers.push(Outlier {
+                    point: p.clone(),
+                    expected,
+                    reasons,
+                });
+            }
+        }
+
+        outliers
+    }
+
+    /// Apply an [OutlierAction] to flagged points, returning a new mesh
+    pub fn apply_outliers(&self, outliers: &[Outlier], action: OutlierAction) -> LevelMap {
+        let flagged = |p: &Point| {
+            outliers
+                .iter()
+                .find(|o| o.point.x == p.x && o.point.y == p.y)
+        };
+
+        let points = self
+            .points
+            .iter()
+            .filter_map(|p| match (flagged(p), action) {
+                (None, _) => Some(p.clone()),
+                (Some(_), OutlierAction::Exclude) => None,
+                (Some(o), OutlierAction::Reweight) => {
+                    let c = match o.expected {
+                        Some(e) => OUTLIER_WEIGHT * p.c + (1.0 - OUTLIER_WEIGHT) * e,
+                        None => p.c,
+                    };
+                    let d = p.d.abs().max(MIN_VARIANCE) / OUTLIER_WEIGHT;
+
+                    Some(Point::new(p.x, p.y, c, d))
+                }
+            })
+            .collect();
+
+        LevelMap {
+            xs: self.xs.clone(),
+            ys: self.ys.clone(),
+            points,
+        }
+    }
+
+    /// Median z offset of the (up to eight) points surrounding a grid point
+    fn neighbour_median(&self, p: &Point) -> Option<f32> {
+        let i = self.xs.iter().position(|x| *x == p.x)? as isize;
+        let j = self.ys.iter().position(|y| *y == p.y)? as isize;
+
+        let mut values = vec![];
+        for dj in -1..=1 {
+            for di in -1..=1 {
+                let (i, j) = (i + di, j + dj);
+                if (di, dj) == (0, 0) || i < 0 || j < 0 {
+                    continue;
+                }
+
+                let (Some(x), Some(y)) = (self.xs.get(i as usize), self.ys.get(j as usize)) else {
+                    continue;
+                };
+                values.extend(self.value(*x, *y).map(|p| p.c));
+            }
+        }
+
+        median(values)
+    }
+}
+
+fn median(mut v: Vec<f32>) -> Option<f32> {
+    if v.is_empty() {
+        return None;
+    }
+
+    v.sort_by(f32::total_cmp);
+    let n = v.len();
+
+    match n % 2 {
+        0 => Some((v[n / 2 - 1] + v[n / 2]) / 2.0),
+        _ => Some(v[n / 2]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::level::DEFAULT_BED_SIZE;
+
+    /// Flat 3x3 mesh with a spike at the centre and a noisy corner
+    fn mesh() -> LevelMap {
+        let mut points = vec![];
+        for y in [0.0, 100.0, 200.0] {
+            for x in [0.0, 100.0, 200.0] {
+                let (c, d) = match (x, y) {
+                    (100.0, 100.0) => (0.5, 0.01),
+                    (200.0, 200.0) => (0.05, 0.2),
+                    _ => (0.0, 0.01),
+                };
+                points.push(Point::new(x, y, c, d));
+            }
+        }
+        LevelMap::new(points)
+    }
+
+    #[test]
+    fn detect_outliers() {
+        let retries = &[
+            ProbeRetry {
+                x: 0.2,
+                y: 199.9,
+                diff: -0.2,
+            },
+            // Small retry changes are ignored
+            ProbeRetry {
+                x: 100.0,
+                y: 0.0,
+                diff: 0.01,
+            },
+        ];
+
+        let o = mesh().outliers(&OutlierLimits::default(), retries);
+
+        let flagged: Vec<_> = o.iter().map(|o| (o.point.x, o.point.y)).collect();
+        assert_eq!(flagged, vec![(100.0, 100.0), (0.0, 200.0), (200.0, 200.0)]);
+
+        assert_eq!(
+            o[0].reasons,
+            vec![OutlierReason::NeighbourDeviation { deviation: 0.5 }]
+        );
+        assert_eq!(o[0].expected, Some(0.0));
+        assert_eq!(
+            o[1].reasons,
+            vec![OutlierReason::RetryDisagreement { diff: -0.2 }]
+        );
+        assert!(matches!(
+            o[2].reasons[..],
+            [OutlierReason::HighVariance { .. }]
+        ));
+    }
+
+    #[test]
+    fn apply_outliers() {
+        let l = mesh();
+        let o = l.outliers(&OutlierLimits::default(), &[]);
+
+        let e = l.apply_outliers(&o, OutlierAction::Exclude);
+        assert_eq!(e.points.len(), 7);
+        assert!(e.value(100.0, 100.0).is_none());
+
+        let r = l.apply_outliers(&o, OutlierAction::Reweight);
+        let p = r.value(100.0, 100.0).unwrap();
+        assert!((p.c - 0.05).abs() < 1e-6);
+        assert!((p.d - 0.1).abs() < 1e-6);
+
+        // Re-weighting reduces the spike in stats
+        let range = |l: &LevelMap| l.stats(DEFAULT_BED_SIZE).unwrap().range;
+        assert!(range(&r) < range(&l));
+    }
+}
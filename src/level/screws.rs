@@ -0,0 +1,264 @@
+//! Bed screw adjustment guidance, removing tilt from a fitted [LevelMap] plane
+
+use std::{fmt::Display, str::FromStr};
+
+use super::LevelMap;
+
+/// Adjustments smaller than this are reported as level (mm)
+pub const SCREW_TOLERANCE: f32 = 0.01;
+
+/// Bed mount screw location
+#[derive(Clone, PartialEq, Debug)]
+pub struct Screw {
+    pub name: String,
+    /// X location (mm)
+    pub x: f32,
+    /// Y location (mm)
+    pub y: f32,
+}
+
+impl Screw {
+    /// Create a new screw location
+    pub fn new(name: impl ToString, x: f32, y: f32) -> Self {
+        Self {
+            name: name.to_string(),
+            x,
+            y,
+        }
+    }
+
+    /// Screws at the corners of a mesh, assuming Y increases towards the back of the bed
+    pub fn corners(l: &LevelMap) -> Vec<Screw> {
+        let (Some(x0), Some(x1), Some(y0), Some(y1)) =
+            (l.xs.first(), l.xs.last(), l.ys.first(), l.ys.last())
+        else {
+            return vec![];
+        };
+
+        vec![
+            Screw::new("front left", *x0, *y0),
+            Screw::new("front right", *x1, *y0),
+            Screw::new("back right", *x1, *y1),
+            Screw::new("back left", *x0, *y1),
+        ]
+    }
+}
+
+/// Parse screw locations from `[name:]x,y`
+impl FromStr for Screw {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, loc) = match s.split_once(':') {
+            Some((n, l)) => (n.trim().to_string(), l),
+            None => (s.trim().to_string(), s),
+        };
+
+        let (x, y) = loc
+            .split_once(',')
+            .ok_or_else(|| format!("invalid screw '{s}', expected [name:]x,y"))?;
+        let parse = |v: &str| {
+            v.trim()
+                .parse::<f32>()
+                .map_err(|e| format!("invalid screw '{s}': {e}"))
+        };
+
+        Ok(Self {
+            name,
+            x: parse(x)?,
+            y: parse(y)?,
+        })
+    }
+}
+
+/// Knob rotation, viewed from the knob
+#[derive(Clone, Copy, PartialEq, Eq, Debug, clap::ValueEnum, displaydoc::Display)]
+pub enum Rotation {
+    /// clockwise
+    Clockwise,
+    /// counter-clockwise
+    CounterClockwise,
+}
+
+impl Rotation {
+    /// Opposite rotation
+    pub fn reverse(&self) -> Self {
+        match self {
+            Self::Clockwise => Self::CounterClockwise,
+            Self::CounterClockwise => Self::Clockwise,
+        }
+    }
+}
+
+/// Bed screw thread
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ScrewThread {
+    /// Thread pitch (mm per turn)
+    pub pitch: f32,
+    /// Rotation that raises the bed
+    pub raise: Rotation,
+}
+
+impl Default for ScrewThread {
+    /// M3 thread, clockwise to raise
+    fn default() -> Self {
+        Self {
+            pitch: 0.5,
+            raise: Rotation::Clockwise,
+        }
+    }
+}
+
+/// Adjustment for a single bed screw
+#[derive(Clone, PartialEq, Debug)]
+pub struct ScrewAdjustment {
+    pub screw: Screw,
+    /// Height change at the screw (mm), positive to raise the bed
+    pub offset: f32,
+    /// Number of turns
+    pub turns: f32,
+    /// Rotation direction
+    pub rotation: Rotation,
+}
+
+impl ScrewAdjustment {
+    /// Check whether the screw is already level with the reference
+    pub fn is_level(&self) -> bool {
+        self.offset.abs() < SCREW_TOLERANCE
+    }
+}
+
+impl Display for ScrewAdjustment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({:.01}, {:.01}): ",
+            self.screw.name, self.screw.x, self.screw.y
+        )?;
+
+        let action = match self.offset > 0.0 {
+            true => "raise",
+            false => "lower",
+        };
+
+        match self.is_level() {
+            true => write!(f, "level"),
+            false => write!(
+                f,
+                "{:.02} turns {} ({action} {:.03}mm)",
+                self.turns,
+                self.rotation,
+                self.offset.abs()
+            ),
+        }
+    }
+}
+
+impl LevelMap {
+    /// Compute screw adjustments to remove tilt, leaving the `base` screw unchanged
+    ///
+    /// Uses the variance weighted plane fit, returning `None` for an empty mesh,
+    /// an invalid `base` index or a non-positive or non-finite thread pitch.
+    pub fn screw_adjustments(
+        &self,
+        screws: &[Screw],
+        thread: &ScrewThread,
+        base: usize,
+    ) -> Option<Vec<ScrewAdjustment>> {
+        if self.points.is_empty() || !thread.pitch.is_finite() || thread.pitch <= 0.0 {
+            return None;
+        }
+
+        let plane = self.fit_plane();
+        let reference = screws.get(base).map(|s| plane.at(s.x, s.y))?;
+
+        let adjustments = screws
+            .iter()
+            .map(|s| {
+                // Raise low points and lower high points to meet the reference
+                let offset = reference - plane.at(s.x, s.y);
+                let rotation = match offset >= 0.0 {
+                    true => thread.raise,
+                    false => thread.raise.reverse(),
+                };
+
+                ScrewAdjustment {
+                    screw: s.clone(),
+                    offset,
+                    turns: offset.abs() / thread.pitch,
+                    rotation,
+                }
+            })
+            .collect();
+
+        Some(adjustments)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::level::Point;
+
+    /// Bed tilted up 0.5mm towards the right
+    fn tilted() -> LevelMap {
+        let mut points = vec![];
+        for y in [0.0, 100.0, 200.0] {
+            for x in [0.0, 100.0, 200.0] {
+                points.push(Point::new(x, y, x / 400.0, 0.01));
+            }
+        }
+        LevelMap::new(points)
+    }
+
+    #[test]
+    fn parse_screws() {
+        assert_eq!(
+            Screw::from_str("front left: 10, 20.5"),
+            Ok(Screw::new("front left", 10.0, 20.5))
+        );
+        assert_eq!(
+            Screw::from_str("10,20"),
+            Ok(Screw::new("10,20", 10.0, 20.0))
+        );
+        assert!(Screw::from_str("a:10").is_err());
+    }
+
+    #[test]
+    fn adjust_tilt() {
+        let l = tilted();
+        let screws = Screw::corners(&l);
+
+        let a = l
+            .screw_adjustments(&screws, &ScrewThread::default(), 0)
+            .unwrap();
+
+        // Front left is the reference, right side screws lowered by one turn
+        let turns: Vec<_> = a
+            .iter()
+            .map(|a| (a.turns * 100.0).round() / 100.0)
+            .collect();
+        assert_eq!(turns, vec![0.0, 1.0, 1.0, 0.0]);
+        assert!(a[0].is_level() && a[3].is_level());
+        assert!((a[1].offset + 0.5).abs() < 1e-4);
+        assert_eq!(a[1].rotation, Rotation::CounterClockwise);
+
+        // Referenced to the right hand side, left screws raised
+        let thread = ScrewThread {
+            pitch: 0.7,
+            raise: Rotation::CounterClockwise,
+        };
+        let a = l.screw_adjustments(&screws, &thread, 1).unwrap();
+        assert!((a[0].turns - 0.5 / 0.7).abs() < 1e-4);
+        assert_eq!(a[0].rotation, Rotation::CounterClockwise);
+
+        assert!(l.screw_adjustments(&screws, &thread, 4).is_none());
+
+        // Non-positive or invalid pitches are rejected
+        for pitch in [0.0, -0.5, f32::NAN, f32::INFINITY] {
+            let thread = ScrewThread { pitch, ..thread };
+            assert!(l.screw_adjustments(&screws, &thread, 0).is_none());
+        }
+    }
+}
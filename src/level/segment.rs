@@ -0,0 +1,292 @@
+//! Splitting log captures into individual auto bed levelling runs
+
+use std::{str::FromStr, time::Duration};
+
+use chrono::{DateTime, Utc};
+
+use super::{GridReport, LevelMap, Point, ProbeRetry, GRID_TOLERANCE};
+use crate::{
+    log::LogRecord,
+    types::{Action, McPrintCommand, McPrintValue, Report},
+};
+
+/// Default maximum gap between measurements within a single run
+pub const RUN_GAP: Duration = Duration::from_secs(60);
+
+/// Auto bed levelling run
+#[derive(Clone, PartialEq, Debug)]
+pub struct LevelRun {
+    /// Time of the first measurement, if known
+    pub start: Option<DateTime<Utc>>,
+    /// Time of the last measurement, if known
+    pub end: Option<DateTime<Utc>>,
+    /// Measured mesh
+    pub map: LevelMap,
+    /// Missing and duplicate points within the mesh
+    pub grid: GridReport,
+    /// Probe retries reported during the run
+    pub retries: Vec<ProbeRetry>,
+}
+
+/// Splits a stream of log records into levelling runs
+///
+/// A new run is started when the printer enters the [Action::Abl] stage, when a coordinate
+/// is measured twice (other than following a probe retry), or when measurements are more
+/// than `gap` apart. Runs end when the printer leaves the [Action::Abl] stage.
+#[derive(Clone, Debug)]
+pub struct Segmenter {
+    tolerance: f32,
+    gap: Duration,
+    stage: Option<Action>,
+    points: Vec<Point>,
+    retries: Vec<ProbeRetry>,
+    /// Retry awaiting its re-probe
+    retry: Option<ProbeRetry>,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+}
+
+impl Default for Segmenter {
+    fn default() -> Self {
+        Self::new(GRID_TOLERANCE, RUN_GAP)
+    }
+}
+
+impl Segmenter {
+    /// Create a new segmenter with the provided grid tolerance (mm) and maximum measurement gap
+    pub fn new(tolerance: f32, gap: Duration) -> Self {
+        Self {
+            tolerance,
+            gap,
+            stage: None,
+            points: vec![],
+            retries: vec![],
+            retry: None,
+            start: None,
+            end: None,
+        }
+    }
+
+    /// Handle a log record, returning any completed run
+    pub fn push(&mut self, r: &LogRecord) -> Option<LevelRun> {
+        match &r.report {
+            Report::Print { value, .. } => {
+                // Partial reports may not include the stage
+                let stage = value.stage()?;
+                let prev = self.stage.replace(stage);
+
+                match (prev, stage) {
+                    (Some(Action::Abl), Action::Abl) => None,
+                    (_, Action::Abl) | (Some(Action::Abl), _) => self.flush(),
+                    _ => None,
+                }
+            }
+            Report::McPrint {
+                command: McPrintCommand::PushInfo,
+                param,
+                ..
+            } => match McPrintValue::from_str(param.as_str()?) {
+                Ok(McPrintValue::BmcMeas { x, y, z_c, z_d }) => {
+                    self.measure(Point::new(x, y, z_c, z_d), r.timestamp)
+                }
+                Ok(McPrintValue::BmcPrevDiff {
+                    x,
+                    y,
+                    prev_z_c_diff,
+                }) => {
+                    let retry = ProbeRetry {
+                        x,
+                        y,
+                        diff: prev_z_c_diff,
+                    };
+                    self.retries.push(retry);
+                    self.retry = Some(retry);
+                    None
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Last reported printer stage
+    pub fn stage(&self) -> Option<Action> {
+        self.stage
+    }
+
+    /// Measurements in the current run
+    pub fn points(&self) -> &[Point] {
+        &self.points
+    }
+
+    /// Finish the current run, if any
+    pub fn finish(&mut self) -> Option<LevelRun> {
+        self.flush()
+    }
+
+    fn measure(&mut self, p: Point, t: Option<DateTime<Utc>>) -> Option<LevelRun> {
+        let gap = match (self.end, t) {
+            (Some(end), Some(t)) => (t - end).to_std().map(|d| d > self.gap).unwrap_or(false),
+            _ => false,
+        };
+        let near =
+            |x: f32, y: f32| (x - p.x).abs() <= self.tolerance && (y - p.y).abs() <= self.tolerance;
+        // Only the measurement immediately following a retry is an expected re-probe
+        let retried = self.retry.take().is_some_and(|r| near(r.x, r.y));
+        let repeated = self.points.iter().any(|v| near(v.x, v.y)) && !retried;
+
+        let run = match gap || repeated {
+            true => self.flush(),
+            false => None,
+        };
+
+        self.points.push(p);
+        self.start = self.start.or(t);
+        self.end = t.or(self.end);
+
+        run
+    }
+
+    fn flush(&mut self) -> Option<LevelRun> {
+        if self.points.is_empty() {
+            return None;
+        }
+
+        let points = std::mem::take(&mut self.points);
+        self.retry = None;
+        let (map, grid) = LevelMap::with_tolerance(points, self.tolerance);
+
+        Some(LevelRun {
+            start: self.start.take(),
+            end: self.end.take(),
+            map,
+            grid,
+            retries: std::mem::take(&mut self.retries),
+        })
+    }
+}
+
+/// Split log records into levelling runs using the default [Segmenter]
+pub fn segment(records: impl IntoIterator<Item = LogRecord>) -> Vec<LevelRun> {
+    let mut s = Segmenter::default();
+
+    let mut runs: Vec<_> = records.into_iter().filter_map(|r| s.push(&r)).collect();
+    runs.extend(s.finish());
+
+    runs
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use serde_json::json;
+
+    use crate::types::PrintValue;
+
+    fn meas(x: f32, y: f32, z: f32, t: i64) -> LogRecord {
+        LogRecord {
+            timestamp: DateTime::from_timestamp(t, 0),
+            report: Report::McPrint {
+                command: McPrintCommand::PushInfo,
+                sequence_id: "1".to_string(),
+                param: json!(format!("[BMC] X{x:.1} Y{y:.1},z_c=  {z}  ,z_d=0.01")),
+            },
+        }
+    }
+
+    fn stage(s: Action, t: i64) -> LogRecord {
+        LogRecord {
+            timestamp: DateTime::from_timestamp(t, 0),
+            report: Report::Print {
+                command: Default::default(),
+                sequence_id: "1".to_string(),
+                value: PrintValue {
+                    stg_cur: Some(s as i32),
+                    ..Default::default()
+                },
+            },
+        }
+    }
+
+    fn grid(z: f32, t: i64) -> Vec<LogRecord> {
+        vec![
+            meas(0.0, 0.0, z, t),
+            meas(100.0, 0.0, z, t + 1),
+            meas(0.0, 100.0, z, t + 2),
+            meas(100.0, 100.0, z, t + 3),
+        ]
+    }
+
+    #[test]
+    fn split_on_stage() {
+        let mut records = vec![stage(Action::Abl, 0)];
+        records.extend(grid(0.1, 1));
+        records.push(stage(Action::Printing, 10));
+        // Partial grid outside of a levelling stage
+        records.extend(grid(0.2, 20).into_iter().step_by(3));
+        records.push(stage(Action::Abl, 30));
+        records.extend(grid(0.3, 31));
+
+        let runs = segment(records);
+
+        assert_eq!(runs.len(), 3);
+        assert_eq!(runs[0].map.points.len(), 4);
+        assert_eq!(runs[0].start, DateTime::from_timestamp(1, 0));
+        assert_eq!(runs[0].end, DateTime::from_timestamp(4, 0));
+        assert_eq!(runs[1].grid.missing.len(), 2);
+        assert_eq!(runs[2].map.points[0].c, 0.3);
+        assert!(runs[2].grid.is_complete());
+    }
+
+    #[test]
+    fn keep_retries() {
+        let mut records = grid(0.1, 0);
+        records.insert(
+            2,
+            LogRecord {
+                timestamp: None,
+                report: Report::McPrint {
+                    command: McPrintCommand::PushInfo,
+                    sequence_id: "1".to_string(),
+                    param: json!("[BMC] PX100.0 Y0.0,prev_z_c_diff=      -0.286      "),
+                },
+            },
+        );
+        // Re-probe following the retry
+        records.insert(3, meas(100.0, 0.0, 0.2, 2));
+
+        let runs = segment(records.clone());
+
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].retries.len(), 1);
+        assert_eq!(runs[0].grid.duplicates.len(), 1);
+        assert_eq!(runs[0].map.value(100.0, 0.0).map(|p| p.c), Some(0.2));
+
+        // A later repeat at a retried point starts a new run
+        records.push(meas(100.0, 0.0, 0.3, 10));
+
+        let runs = segment(records);
+
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[1].map.points.len(), 1);
+        assert!(runs[1].retries.is_empty());
+    }
+
+    #[test]
+    fn split_on_repeat_and_gap() {
+        let mut records = grid(0.1, 0);
+        // Repeated coordinates without a stage change
+        records.extend(grid(0.2, 10));
+        // Partial grid after a long gap, then the remainder
+        records.extend(grid(0.3, 1000).into_iter().take(2));
+        records.extend(grid(0.4, 2000).into_iter().skip(2));
+
+        let runs = segment(records);
+
+        let z: Vec<_> = runs.iter().map(|r| r.map.points[0].c).collect();
+        assert_eq!(z, vec![0.1, 0.2, 0.3, 0.4]);
+        assert!(runs[1].grid.duplicates.is_empty());
+        assert_eq!(runs[3].start, DateTime::from_timestamp(2002, 0));
+    }
+}
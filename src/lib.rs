@@ -12,7 +12,15 @@ use tokio::{
 };
 use tracing::{debug, trace};
 
+pub mod ams;
+pub mod control;
+pub mod files;
+pub mod job;
 pub mod level;
+pub mod log;
+pub mod model;
+pub mod preflight;
+pub mod threemf;
 pub mod types;
 
 mod printer;
@@ -32,9 +40,17 @@ pub struct ConnectOpts {
     #[clap(short, long, default_value = "8883")]
     pub port: u16,
 
+    /// FTPS Port
+    #[clap(long, default_value = "990")]
+    pub ftp_port: u16,
+
     /// Access code (see local connection page on printer)
     #[clap(long, env)]
     pub access_code: String,
+
+    /// Printer serial number, detected from incoming reports if not set
+    #[clap(long, env)]
+    pub serial: Option<String>,
 }
 
 impl Default for ConnectOpts {
@@ -42,7 +58,9 @@ impl Default for ConnectOpts {
         Self {
             hostname: Default::default(),
             port: 8883,
+            ftp_port: 990,
             access_code: Default::default(),
+            serial: None,
         }
     }
 }
@@ -0,0 +1,424 @@
+//! Incremental log file parsing
+//!
+//! Reads [Report]s from JSON arrays, JSONL or concatenated JSON without loading the whole file,
+//! skipping and counting records that fail to parse. Records may be bare reports or [LogRecord]s
+//! with receive timestamps, as written by the `log` command.
+
+use std::io::BufRead;
+
+use chrono::{DateTime, Utc};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use tracing::{debug, warn};
+
+use crate::types::Report;
+
+/// Maximum record length, longer records are skipped to resynchronise on corrupt input
+pub const MAX_RECORD_LEN: usize = 4 * 1024 * 1024;
+
+/// Log parsing statistics
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct LogStats {
+    /// Records decoded
+    pub records: usize,
+    /// Records skipped due to errors
+    pub skipped: usize,
+    /// Bytes read
+    pub bytes: u64,
+}
+
+/// Logged report with receive timestamp
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct LogRecord {
+    /// Receive time, `None` for bare reports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<Utc>>,
+    pub report: Report,
+}
+
+impl LogRecord {
+    /// Create a new record received now
+    pub fn now(report: Report) -> Self {
+        Self {
+            timestamp: Some(Utc::now()),
+            report,
+        }
+    }
+}
+
+/// Logged record formats
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawRecord {
+    Timestamped(LogRecord),
+    Bare(Report),
+}
+
+impl From<RawRecord> for LogRecord {
+    fn from(value: RawRecord) -> Self {
+        match value {
+            RawRecord::Timestamped(r) => r,
+            RawRecord::Bare(report) => Self {
+                timestamp: None,
+                report,
+            },
+        }
+    }
+}
+
+/// Splits a byte stream into top-level JSON objects
+#[derive(Debug, Default)]
+struct Splitter {
+    buff: Vec<u8>,
+    depth: usize,
+    in_string: bool,
+    escape: bool,
+    skipping: bool,
+    offset: u64,
+    start: u64,
+}
+
+/// Split result, a complete record or the offset of invalid data
+type Split = Result<Vec<u8>, u64>;
+
+impl Splitter {
+    /// Feed a byte, returning any completed record
+    fn push(&mut self, b: u8) -> Option<Split> {
+        let offset = self.offset;
+        self.offset += 1;
+
+        // Between records, skip separators and array brackets
+        if self.depth == 0 {
+            match b {
+                b'{' => {
+                    self.buff.clear();
+                    self.buff.push(b);
+                    self.depth = 1;
+                    self.skipping = false;
+                    self.start = offset;
+                }
+                b',' | b'[' | b']' => (),
+                b if b.is_ascii_whitespace() => (),
+                // Report each run of invalid data once
+                _ if !self.skipping => {
+                    self.skipping = true;
+                    return Some(Err(offset));
+                }
+                _ => (),
+            }
+
+            return None;
+        }
+
+        self.buff.push(b);
+
+        match (self.in_string, self.escape, b) {
+            (true, true, _) => self.escape = false,
+            (true, false, b'\\') => self.escape = true,
+            (true, false, b'"') => self.in_string = false,
+            (true, false, _) => (),
+            (false, _, b'"') => self.in_string = true,
+            (false, _, b'{' | b'[') => self.depth += 1,
+            (false, _, b'}' | b']') => {
+                self.depth -= 1;
+                if self.depth == 0 {
+                    return Some(Ok(std::mem::take(&mut self.buff)));
+                }
+            }
+            _ => (),
+        }
+
+        if self.buff.len() > MAX_RECORD_LEN {
+            self.reset();
+            self.skipping = true;
+            return Some(Err(self.start));
+        }
+
+        None
+    }
+
+    /// Finish input, returning the offset of any incomplete record
+    fn finish(&mut self) -> Option<Split> {
+        match self.depth {
+            0 => None,
+            _ => {
+                self.reset();
+                Some(Err(self.start))
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.buff.clear();
+        self.depth = 0;
+        self.in_string = false;
+        self.escape = false;
+    }
+}
+
+/// Shared record decoding
+#[derive(Debug, Default)]
+struct Decoder {
+    splitter: Splitter,
+    stats: LogStats,
+}
+
+impl Decoder {
+    /// Feed a buffer, returning the bytes consumed and any decoded record
+    fn feed(&mut self, buff: &[u8]) -> (usize, Option<LogRecord>) {
+        for (i, b) in buff.iter().enumerate() {
+            if let Some(s) = self.splitter.push(*b) {
+                self.stats.bytes += i as u64 + 1;
+                return (i + 1, self.decode(s));
+            }
+        }
+
+        self.stats.bytes += buff.len() as u64;
+        (buff.len(), None)
+    }
+
+    /// Handle end of input
+    fn finish(&mut self) -> Option<LogRecord> {
+        let s = self.splitter.finish()?;
+        self.decode(s)
+    }
+
+    fn decode(&mut self, s: Split) -> Option<LogRecord> {
+        let offset = self.splitter.start;
+
+        let r = match s {
+            Ok(d) => serde_json::from_slice::<RawRecord>(&d),
+            Err(offset) => {
+                debug!("Skipping invalid data at offset {offset}");
+                self.stats.skipped += 1;
+                return None;
+            }
+        };
+
+        match r {
+            Ok(r) => {
+                self.stats.records += 1;
+                Some(r.into())
+            }
+            Err(e) => {
+                debug!("Skipping record at offset {offset}: {e}");
+                self.stats.skipped += 1;
+                None
+            }
+        }
+    }
+}
+
+/// Iterator over [Report]s in a log file
+pub struct LogReader<R> {
+    r: R,
+    decoder: Decoder,
+    done: bool,
+}
+
+impl<R: BufRead> LogReader<R> {
+    /// Create a new log reader
+    pub fn new(r: R) -> Self {
+        Self {
+            r,
+            decoder: Decoder::default(),
+            done: false,
+        }
+    }
+
+    /// Fetch parsing statistics
+    pub fn stats(&self) -> LogStats {
+        self.decoder.stats
+    }
+
+    /// Read the next record, including the receive timestamp where available
+    pub fn next_record(&mut self) -> Option<LogRecord> {
+        while !self.done {
+            let buff = match self.r.fill_buf() {
+                Ok(b) => b,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    warn!("Log read failed: {e}");
+                    self.done = true;
+                    break;
+                }
+            };
+
+            if buff.is_empty() {
+                self.done = true;
+                return self.decoder.finish();
+            }
+
+            let (n, r) = self.decoder.feed(buff);
+            self.r.consume(n);
+
+            if r.is_some() {
+                return r;
+            }
+        }
+
+        None
+    }
+
+    /// Convert into an iterator of timestamped records
+    pub fn records(mut self) -> impl Iterator<Item = LogRecord> {
+        std::iter::from_fn(move || self.next_record())
+    }
+}
+
+impl<R: BufRead> Iterator for LogReader<R> {
+    type Item = Report;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().map(|r| r.report)
+    }
+}
+
+/// Async reader for [Report]s in a log file
+pub struct AsyncLogReader<R> {
+    r: R,
+    decoder: Decoder,
+    done: bool,
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncLogReader<R> {
+    /// Create a new async log reader
+    pub fn new(r: R) -> Self {
+        Self {
+            r,
+            decoder: Decoder::default(),
+            done: false,
+        }
+    }
+
+    /// Fetch parsing statistics
+    pub fn stats(&self) -> LogStats {
+        self.decoder.stats
+    }
+
+    /// Read the next report
+    pub async fn next(&mut self) -> Option<Report> {
+        self.next_record().await.map(|r| r.report)
+    }
+
+    /// Read the next record, including the receive timestamp where available
+    pub async fn next_record(&mut self) -> Option<LogRecord> {
+        while !self.done {
+            let buff = match self.r.fill_buf().await {
+                Ok(b) => b,
+                Err(e) => {
+                    warn!("Log read failed: {e}");
+                    self.done = true;
+                    break;
+                }
+            };
+
+            if buff.is_empty() {
+                self.done = true;
+                return self.decoder.finish();
+            }
+
+            let (n, r) = self.decoder.feed(buff);
+            self.r.consume(n);
+
+            if r.is_some() {
+                return r;
+            }
+        }
+
+        None
+    }
+
+    /// Convert into a [Stream] of reports
+    pub fn into_stream(self) -> impl Stream<Item = Report> {
+        futures::stream::unfold(self, |mut r| async move { r.next().await.map(|v| (v, r)) })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use futures::StreamExt;
+
+    use crate::types::PrintValue;
+
+    const REPORT: &str =
+        r#"{"print": {"command": "push_status", "sequence_id": "1", "bed_temper": 60.0}}"#;
+
+    fn bed_temps(reports: impl Iterator<Item = Report>) -> Vec<Option<f32>> {
+        reports
+            .map(|r| match r {
+                Report::Print {
+                    value: PrintValue { bed_temper, .. },
+                    ..
+                } => bed_temper,
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn read_formats() {
+        let tests = &[
+            // Concatenated, as written by the `log` command
+            format!("{REPORT}{REPORT}{REPORT}"),
+            // JSONL
+            format!("{REPORT}\n{REPORT}\n{REPORT}\n"),
+            // Array
+            format!("[\n  {REPORT},\n  {REPORT},\n  {REPORT}\n]"),
+        ];
+
+        for t in tests {
+            let mut r = LogReader::new(t.as_bytes());
+            assert_eq!(bed_temps(&mut r), vec![Some(60.0); 3], "{t}");
+            assert_eq!(r.stats().records, 3);
+            assert_eq!(r.stats().skipped, 0);
+            assert_eq!(r.stats().bytes, t.len() as u64);
+        }
+    }
+
+    #[test]
+    fn skip_bad_records() {
+        let data = format!(
+            "{REPORT}\n{{\"print\": {{\"unknown\": true}}}}\ngarbage\n{{\"text\": \"}}{{\\\"\"}}\n{REPORT}\n{{\"truncated\": "
+        );
+
+        let mut r = LogReader::new(data.as_bytes());
+        assert_eq!(bed_temps(&mut r), vec![Some(60.0); 2]);
+
+        assert_eq!(r.stats().records, 2);
+        assert_eq!(r.stats().skipped, 4);
+    }
+
+    #[test]
+    fn read_timestamps() {
+        let data = format!(
+            "{{\"timestamp\": \"2024-01-02T03:04:05Z\", \"report\": {REPORT}}}\n{REPORT}\n"
+        );
+
+        let records: Vec<_> = LogReader::new(data.as_bytes()).records().collect();
+
+        assert_eq!(
+            records.iter().map(|r| r.timestamp).collect::<Vec<_>>(),
+            vec![Some("2024-01-02T03:04:05Z".parse().unwrap()), None]
+        );
+        assert_eq!(
+            bed_temps(records.into_iter().map(|r| r.report)),
+            vec![Some(60.0); 2]
+        );
+    }
+
+    #[tokio::test]
+    async fn read_stream() {
+        let data = format!("{REPORT}\n{REPORT}");
+
+        let reports: Vec<_> = AsyncLogReader::new(data.as_bytes())
+            .into_stream()
+            .collect()
+            .await;
+
+        assert_eq!(bed_temps(reports.into_iter()), vec![Some(60.0); 2]);
+    }
+}
@@ -1,13 +1,25 @@
-use std::{io::Write, str::FromStr};
+use std::{io::Write, path::PathBuf, str::FromStr, time::Duration};
 
+use chrono::Utc;
 use clap::Parser;
 use futures::StreamExt;
+use glob::Pattern;
+use serde::{de::IgnoredAny, Serialize};
+use tokio::time::Instant;
 use tracing::{debug, info, warn};
 use tracing_subscriber::{filter::LevelFilter, EnvFilter, FmtSubscriber};
 
 use bambu::{
-    level::{LevelMap, Point},
-    types::{McPrintCommand, McPrintValue, Report},
+    control::{Axis, Motion},
+    files::{FileClient, FileEntry, Progress},
+    level::{
+        bed_size, trend, ColorMode, Heatmap, LevelMap, LevelRun, MeshFormat, MeshRecord, MeshStore,
+        Metric, OutlierAction, OutlierLimits, Rotation, Screw, ScrewThread, Segmenter,
+        GRID_TOLERANCE,
+    },
+    log::{LogReader, LogRecord},
+    threemf::ThreeMf,
+    types::{Action, McPrintValue, Report, CALIBRATE_BED_LEVELING},
     ConnectOpts, Printer,
 };
 
@@ -35,10 +47,350 @@ pub enum Commands {
     },
     /// Parse an existing log file
     Parse {
-        /// Log file for parsing
+        /// Log file for parsing (JSON array, JSONL or concatenated JSON)
         #[clap(long)]
         file: String,
+
+        /// Tolerance for snapping probe coordinates to the levelling grid (mm)
+        #[clap(long, default_value_t = GRID_TOLERANCE)]
+        tolerance: f32,
+
+        /// Maximum gap between measurements in a single levelling run (s)
+        #[clap(long, default_value = "60")]
+        gap: u64,
+
+        /// Levelling run to output (from 1), all runs for `grid` or the last run otherwise
+        #[clap(long)]
+        run: Option<usize>,
+
+        #[clap(flatten)]
+        mesh: MeshOpts,
     },
+    /// Capture an auto bed levelling pass from a connected printer
+    Level {
+        /// Start a bed levelling calibration, otherwise wait for levelling from another source
+        #[clap(long)]
+        calibrate: bool,
+
+        /// Time to wait for the levelling pass to start (s)
+        #[clap(long, default_value = "600")]
+        timeout: u64,
+
+        /// Time without measurements before the pass is considered complete (s)
+        #[clap(long, default_value = "60")]
+        gap: u64,
+
+        #[clap(flatten)]
+        mesh: MeshOpts,
+    },
+    /// Show levelling mesh history and drift for a printer
+    History {
+        /// Mesh history directory
+        #[clap(long)]
+        store: PathBuf,
+
+        /// Only include meshes captured within the specified number of weeks
+        #[clap(long)]
+        weeks: Option<i64>,
+    },
+    /// Home printer axes
+    Home {
+        /// Axes to home, all axes if not specified
+        axes: Vec<Axis>,
+    },
+    /// Move an axis by a relative distance
+    Jog {
+        /// Axis to move
+        axis: Axis,
+
+        /// Distance to move (mm)
+        #[clap(allow_negative_numbers = true)]
+        distance: f32,
+
+        /// Feedrate (mm/min)
+        #[clap(long)]
+        feedrate: Option<f32>,
+    },
+    /// Manage files on printer storage
+    Files {
+        #[clap(subcommand)]
+        cmd: FilesCommand,
+
+        /// Output results as JSON
+        #[clap(long, global = true)]
+        json: bool,
+    },
+    /// Manage timelapse recordings on printer storage
+    Timelapse {
+        #[clap(subcommand)]
+        cmd: TimelapseCommand,
+
+        /// Output results as JSON
+        #[clap(long, global = true)]
+        json: bool,
+    },
+    /// List objects on a plate in a sliced 3MF file
+    Objects {
+        /// Local sliced 3MF file
+        file: PathBuf,
+
+        /// Plate index
+        #[clap(long, default_value = "1")]
+        plate: usize,
+
+        /// Output results as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Skip objects in the current print
+    Skip {
+        /// Object ids, or names when `--file` is set
+        #[clap(required = true)]
+        objects: Vec<String>,
+
+        /// Local copy of the sliced 3MF file, for resolving object names
+        #[clap(long)]
+        file: Option<PathBuf>,
+
+        /// Plate index
+        #[clap(long, default_value = "1")]
+        plate: usize,
+    },
+}
+
+/// Levelling mesh output options
+#[derive(Clone, Debug, PartialEq, Parser)]
+pub struct MeshOpts {
+    /// Output format for the levelling map
+    #[clap(long, value_enum, default_value = "grid")]
+    format: MeshFormat,
+
+    /// Save levelling runs to a mesh history directory, keyed by printer serial
+    #[clap(long)]
+    store: Option<PathBuf>,
+
+    /// Handling for outlier probes in the output, stored meshes are not modified
+    #[clap(long, value_enum)]
+    outliers: Option<OutlierAction>,
+
+    #[clap(flatten)]
+    heatmap: HeatmapOpts,
+
+    #[clap(flatten)]
+    screws: ScrewOpts,
+}
+
+impl MeshOpts {
+    /// Report and print a levelling run
+    fn show(&self, n: usize, r: &LevelRun, serial: Option<&str>) -> anyhow::Result<()> {
+        let format = self.format;
+
+        for p in &r.grid.duplicates {
+            warn!(
+                "Run {n}: replaced duplicate measurement at ({}, {})",
+                p.x, p.y
+            );
+        }
+        for (x, y) in &r.grid.missing {
+            warn!("Run {n}: missing measurement at ({x}, {y})");
+        }
+
+        // Flag outlier probes, optionally excluding or re-weighting them for display and export
+        let flagged = r.map.outliers(&OutlierLimits::default(), &r.retries);
+        for o in &flagged {
+            let reasons: Vec<_> = o.reasons.iter().map(|r| r.to_string()).collect();
+            warn!(
+                "Run {n}: outlier at ({}, {}): {}",
+                o.point.x,
+                o.point.y,
+                reasons.join(", ")
+            );
+        }
+        let map = match self.outliers {
+            Some(a) => r.map.apply_outliers(&flagged, a),
+            None => r.map.clone(),
+        };
+
+        // Headers and statistics accompany the terminal grid, other formats are left clean for tooling
+        if format == MeshFormat::Grid {
+            match r.start {
+                Some(t) => println!("Run {n} ({t})"),
+                None => println!("Run {n}"),
+            }
+        }
+
+        match format {
+            MeshFormat::Grid => self.heatmap.print(&map),
+            _ => map.export(format, &mut std::io::stdout())?,
+        }
+
+        if format == MeshFormat::Grid {
+            if let Some(s) = map.stats(bed_size(serial)) {
+                println!("{s}");
+            }
+            self.screws.print(&map);
+            println!();
+        }
+
+        Ok(())
+    }
+
+    /// Save a levelling run to the mesh store when enabled
+    ///
+    /// Runs without a capture time are skipped, as records are keyed by timestamp.
+    fn store(&self, n: usize, r: &LevelRun, serial: Option<&str>) -> anyhow::Result<()> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+
+        let serial =
+            serial.ok_or_else(|| anyhow::anyhow!("`--serial` required to store meshes"))?;
+        let Some(timestamp) = r.start else {
+            warn!("Run {n} has no timestamp, not storing");
+            return Ok(());
+        };
+
+        // Store the probed mesh, outlier handling is only applied to the output
+        let path = MeshStore::open(store)?.save(&MeshRecord {
+            serial: serial.to_string(),
+            timestamp,
+            map: r.map.clone(),
+        });
+        let path = path.map_err(|e| anyhow::anyhow!("failed to store run {n}: {e}"))?;
+        info!("Saved run {n} to {}", path.display());
+
+        Ok(())
+    }
+}
+
+/// Levelling heatmap options
+#[derive(Clone, Debug, PartialEq, Parser)]
+pub struct HeatmapOpts {
+    /// Heatmap metric
+    #[clap(long, value_enum, default_value = "offset")]
+    metric: Metric,
+
+    /// Heatmap colour output, detected from the terminal if not set
+    #[clap(long, value_enum)]
+    color: Option<ColorMode>,
+}
+
+impl HeatmapOpts {
+    /// Print a heatmap for a mesh
+    fn print(&self, l: &LevelMap) {
+        let mode = self.color.unwrap_or_else(ColorMode::detect);
+        print!("{}", Heatmap::new(l, self.metric, mode));
+    }
+}
+
+/// Bed screw adjustment options
+#[derive(Clone, Debug, PartialEq, Parser)]
+pub struct ScrewOpts {
+    /// Show bed screw adjustments to remove tilt
+    #[clap(long)]
+    adjust: bool,
+
+    /// Bed screw locations as `[name:]x,y` (mm), mesh corners if not set
+    #[clap(long = "screw")]
+    screws: Vec<Screw>,
+
+    /// Screw thread pitch (mm per turn)
+    #[clap(long, default_value = "0.5")]
+    pitch: f32,
+
+    /// Knob rotation that raises the bed
+    #[clap(long, value_enum, default_value = "clockwise")]
+    raise: Rotation,
+
+    /// Reference screw index (from 0), left unadjusted
+    #[clap(long, default_value = "0")]
+    base_screw: usize,
+}
+
+impl ScrewOpts {
+    /// Print screw adjustments for a mesh, if enabled
+    fn print(&self, l: &LevelMap) {
+        if !self.adjust {
+            return;
+        }
+
+        let screws = match self.screws.is_empty() {
+            true => Screw::corners(l),
+            false => self.screws.clone(),
+        };
+        let thread = ScrewThread {
+            pitch: self.pitch,
+            raise: self.raise,
+        };
+
+        match l.screw_adjustments(&screws, &thread, self.base_screw) {
+            Some(a) => {
+                println!("Screw adjustments ({}mm pitch):", self.pitch);
+                for a in a {
+                    println!("  {a}");
+                }
+            }
+            None => warn!("No screw adjustments, empty mesh, invalid base screw or pitch"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Parser)]
+pub enum FilesCommand {
+    /// List a directory, or files matching a glob pattern (e.g. `/cache/*.3mf`)
+    Ls {
+        #[clap(default_value = "/")]
+        path: String,
+    },
+    /// Upload local files (glob patterns supported)
+    Put {
+        #[clap(required = true)]
+        files: Vec<String>,
+
+        /// Destination directory
+        #[clap(long, default_value = "/")]
+        dest: String,
+    },
+    /// Download files matching remote paths or glob patterns
+    Get {
+        #[clap(required = true)]
+        paths: Vec<String>,
+
+        /// Output directory
+        #[clap(long, default_value = ".")]
+        out: PathBuf,
+    },
+    /// Delete files matching remote paths or glob patterns
+    Rm {
+        #[clap(required = true)]
+        paths: Vec<String>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Parser)]
+pub enum TimelapseCommand {
+    /// List timelapse recordings
+    Ls,
+    /// Download timelapse recordings matching a glob pattern
+    Fetch {
+        #[clap(default_value = "*")]
+        pattern: String,
+
+        /// Output directory
+        #[clap(long, default_value = ".")]
+        out: PathBuf,
+    },
+}
+
+/// Timelapse directory on printer storage
+const TIMELAPSE_DIR: &str = "/timelapse";
+
+/// File transfer result for JSON output
+#[derive(Clone, Debug, Serialize)]
+struct Transfer {
+    remote: String,
+    local: Option<String>,
+    bytes: u64,
 }
 
 #[tokio::main]
@@ -66,34 +418,173 @@ async fn main() -> anyhow::Result<()> {
             Some(o) => Some(std::fs::File::create(o)?),
             _ => None,
         },
-        Commands::Parse { file } => {
-            let d = std::fs::read(file)?;
+        Commands::Parse {
+            file,
+            tolerance,
+            gap,
+            run,
+            mesh,
+        } => {
+            let f = std::fs::File::open(file)?;
+            let mut reader = LogReader::new(std::io::BufReader::new(f));
+
+            // Split measurements into levelling runs
+            let mut s = Segmenter::new(tolerance, Duration::from_secs(gap));
+            let mut runs: Vec<_> = std::iter::from_fn(|| reader.next_record())
+                .filter_map(|r| s.push(&r))
+                .collect();
+            runs.extend(s.finish());
+
+            let stats = reader.stats();
+            info!(
+                "Parsed {} objects from {} bytes, skipped {} invalid records, found {} levelling runs",
+                stats.records,
+                stats.bytes,
+                stats.skipped,
+                runs.len()
+            );
+
+            let selected: Vec<_> = match (run, mesh.format) {
+                (Some(n), _) => match runs.get(n.wrapping_sub(1)) {
+                    Some(r) => vec![(n, r)],
+                    None => return Err(anyhow::anyhow!("no run {n}, found {}", runs.len())),
+                },
+                (None, MeshFormat::Grid) => {
+                    runs.iter().enumerate().map(|(i, r)| (i + 1, r)).collect()
+                }
+                (None, _) => {
+                    if runs.len() > 1 {
+                        warn!(
+                            "Found {} runs, exporting the last (use `--run` to select)",
+                            runs.len()
+                        );
+                    }
+                    runs.last().map(|r| (runs.len(), r)).into_iter().collect()
+                }
+            };
+
+            // All runs are stored, independent of the output selection
+            let serial = args.opts.serial.as_deref();
+            for (i, r) in runs.iter().enumerate() {
+                mesh.store(i + 1, r, serial)?;
+            }
 
-            info!("Loaded {} bytes", d.len());
+            for (n, r) in selected {
+                mesh.show(n, r, serial)?;
+            }
+
+            return Ok(());
+        }
+        Commands::Level {
+            calibrate,
+            timeout,
+            gap,
+            mesh,
+        } => {
+            return run_level(args.opts, calibrate, timeout, gap, mesh).await;
+        }
+        Commands::History { store, weeks } => {
+            let serial = args
+                .opts
+                .serial
+                .ok_or_else(|| anyhow::anyhow!("`--serial` required for mesh history"))?;
+
+            let mut records = MeshStore::open(store)?.list(&serial)?;
+            if let Some(w) = weeks {
+                let since = Utc::now() - chrono::Duration::weeks(w);
+                records.retain(|r| r.timestamp >= since);
+            }
+
+            if records.is_empty() {
+                warn!("No stored meshes for {serial}");
+                return Ok(());
+            }
+
+            println!("{}", trend(&records));
+
+            // Show the change across the selected period
+            if let (Some(first), Some(last)) = (records.first(), records.last()) {
+                if records.len() > 1 {
+                    println!();
+                    println!("Change {} to {}", first.timestamp, last.timestamp);
+                    println!("{}", last.map.diff(&first.map));
+                }
+            }
+
+            return Ok(());
+        }
+        Commands::Home { axes } => {
+            return run_motion(args.opts, Motion::Home(axes)).await;
+        }
+        Commands::Jog {
+            axis,
+            distance,
+            feedrate,
+        } => {
+            let m = Motion::Jog {
+                axis,
+                distance,
+                feedrate,
+            };
+            return run_motion(args.opts, m).await;
+        }
+        Commands::Files { cmd, json } => {
+            let mut c = FileClient::connect(&args.opts).await?;
+            run_files(&mut c, cmd, json).await?;
+            c.quit().await?;
 
-            let o: Vec<Report> = serde_json::from_slice(&d)?;
+            return Ok(());
+        }
+        Commands::Timelapse { cmd, json } => {
+            let mut c = FileClient::connect(&args.opts).await?;
+            run_timelapse(&mut c, cmd, json).await?;
+            c.quit().await?;
 
-            info!("Parsed {} objects", o.len());
+            return Ok(());
+        }
+        Commands::Objects { file, plate, json } => {
+            let p = ThreeMf::open(file)?;
+            let plate = p
+                .plate(plate)
+                .ok_or_else(|| anyhow::anyhow!("no plate {plate}"))?;
 
-            let r: Vec<_> = o
-                .iter()
-                .filter_map(|i| match i {
-                    Report::McPrint { command, param, .. }
-                        if command == &McPrintCommand::PushInfo =>
-                    {
-                        Some(param.to_string())
+            match json {
+                true => println!("{}", serde_json::to_string_pretty(&plate.objects)?),
+                false => {
+                    for o in &plate.objects {
+                        let skipped = if o.skipped { " (skipped)" } else { "" };
+                        println!("{:>8}  {}{skipped}", o.identify_id, o.name);
                     }
-                    _ => None,
-                })
-                .filter_map(|p| McPrintValue::from_str(&p).ok())
-                .filter_map(|v| match v {
-                    McPrintValue::BmcMeas { x, y, z_c, z_d } => Some(Point::new(x, y, z_c, z_d)),
-                    _ => None,
-                })
-                .collect();
+                }
+            }
+
+            return Ok(());
+        }
+        Commands::Skip {
+            objects,
+            file,
+            plate,
+        } => {
+            let ids = match file {
+                Some(f) => {
+                    let p = ThreeMf::open(f)?;
+                    p.plate(plate)
+                        .ok_or_else(|| anyhow::anyhow!("no plate {plate}"))?
+                        .object_ids(&objects)?
+                }
+                None => objects
+                    .iter()
+                    .map(|o| o.parse::<u64>())
+                    .collect::<Result<_, _>>()?,
+            };
+
+            let p = Printer::connect(args.opts).await?;
+            p.refresh().await?;
+
+            p.skip_objects(&ids).await?;
+            info!("Skipped objects {ids:?}");
 
-            let l = LevelMap::new(r);
-            println!("{l}");
+            p.disconnect().await?;
 
             return Ok(());
         }
@@ -122,12 +613,341 @@ async fn main() -> anyhow::Result<()> {
                 }
             };
 
-            // Write to log if enabled
+            // Write to log if enabled, wrapping valid JSON with the receive time
             if let Some(f) = &mut f {
-                f.write(data.as_bytes())?;
+                match serde_json::from_str::<IgnoredAny>(&data) {
+                    Ok(_) => writeln!(
+                        f,
+                        r#"{{"timestamp":"{}","report":{data}}}"#,
+                        Utc::now().to_rfc3339()
+                    )?,
+                    Err(_) => writeln!(f, "{data}")?,
+                }
             }
         }
     }
 
     Ok(())
 }
+
+/// Connect to the printer and execute a maintenance motion
+async fn run_motion(opts: ConnectOpts, motion: Motion) -> anyhow::Result<()> {
+    let p = Printer::connect(opts).await?;
+
+    let status = p.refresh().await?;
+    debug!("Printer state: {:?}", status.gcode_state);
+
+    p.motion(&motion).await?;
+    info!("Sent {motion:?}");
+
+    p.disconnect().await?;
+
+    Ok(())
+}
+
+/// Connect to the printer and capture an auto bed levelling pass
+async fn run_level(
+    opts: ConnectOpts,
+    calibrate: bool,
+    timeout: u64,
+    gap: u64,
+    mesh: MeshOpts,
+) -> anyhow::Result<()> {
+    let p = Printer::connect(opts).await?;
+
+    let status = p.refresh().await?;
+    debug!("Printer state: {:?}", status.gcode_state);
+
+    // Subscribe before starting calibration so no measurements are missed
+    let mut rx = p.listen()?;
+
+    if calibrate {
+        p.calibrate(CALIBRATE_BED_LEVELING).await?;
+        info!("Started bed levelling calibration");
+    }
+
+    let gap = Duration::from_secs(gap);
+    let mut s = Segmenter::new(GRID_TOLERANCE, gap);
+
+    // Wait for the pass to start, then until the printer leaves levelling or measurements stop.
+    // Deadlines run from the start and the last measurement, as status reports arrive continuously
+    let mut deadline = Instant::now() + Duration::from_secs(timeout);
+
+    let run = loop {
+        let data = match tokio::time::timeout_at(deadline, rx.recv()).await {
+            Ok(Some((_topic, data))) => data,
+            Ok(None) | Err(_) => break s.finish(),
+        };
+        let Ok(report) = serde_json::from_str::<Report>(&data) else {
+            continue;
+        };
+
+        let measured = match &report {
+            Report::McPrint { param, .. } => param
+                .as_str()
+                .and_then(|p| McPrintValue::from_str(p).ok())
+                .is_some_and(|v| v.is_bmc_meas()),
+            _ => false,
+        };
+
+        let stage = s.stage();
+        let run = s.push(&LogRecord::now(report));
+
+        // Runs only complete on leaving the levelling stage, other splits restart the pass
+        match run {
+            Some(r) if stage == Some(Action::Abl) && s.stage() != Some(Action::Abl) => {
+                break Some(r)
+            }
+            Some(r) => {
+                eprintln!();
+                warn!(
+                    "Levelling restarted, discarding {} earlier measurements",
+                    r.map.points.len()
+                );
+            }
+            None => (),
+        }
+
+        if let Some(l) = s.points().last().filter(|_| measured) {
+            deadline = Instant::now() + gap;
+
+            eprint!(
+                "\rProbing: {} points, last ({:.1}, {:.1}) z={:.03}  ",
+                s.points().len(),
+                l.x,
+                l.y,
+                l.c
+            );
+        }
+    };
+    eprintln!();
+
+    let Some(run) = run else {
+        p.disconnect().await?;
+        return Err(anyhow::anyhow!(
+            "no levelling measurements received within {timeout}s"
+        ));
+    };
+    info!("Levelling complete, {} points", run.map.points.len());
+
+    let serial = p.serial();
+    mesh.show(1, &run, serial.as_deref())?;
+    mesh.store(1, &run, serial.as_deref())?;
+
+    p.disconnect().await?;
+
+    Ok(())
+}
+
+/// Execute file management commands
+async fn run_files(c: &mut FileClient, cmd: FilesCommand, json: bool) -> anyhow::Result<()> {
+    match cmd {
+        FilesCommand::Ls { path } => {
+            let files = match has_glob(&path) {
+                true => remote_matches(c, &path).await?,
+                false => c.list(&path).await?,
+            };
+            print_files(&files, json)?;
+        }
+        FilesCommand::Put { files, dest } => {
+            let mut results = vec![];
+
+            for f in files {
+                let paths: Vec<_> = match has_glob(&f) {
+                    true => glob::glob(&f)?.collect::<Result<_, _>>()?,
+                    false => vec![PathBuf::from(&f)],
+                };
+
+                if paths.is_empty() {
+                    warn!("No local files matching {f}");
+                }
+
+                for p in paths.iter().filter(|p| p.is_file()) {
+                    let name = p.file_name().unwrap_or_default().to_string_lossy();
+                    let remote = format!("{}/{name}", dest.trim_end_matches('/'));
+
+                    let bytes = c.upload_file(p, &remote, progress(&remote, json)).await?;
+                    print_done(&remote, json);
+
+                    results.push(Transfer {
+                        remote,
+                        local: Some(p.display().to_string()),
+                        bytes,
+                    });
+                }
+            }
+
+            print_transfers(&results, json)?;
+        }
+        FilesCommand::Get { paths, out } => {
+            let mut results = vec![];
+
+            for p in paths {
+                results.extend(download_matches(c, &p, &out, json).await?);
+            }
+
+            print_transfers(&results, json)?;
+        }
+        FilesCommand::Rm { paths } => {
+            let mut results = vec![];
+
+            for p in paths {
+                let files = remote_matches(c, &p).await?;
+                if files.is_empty() {
+                    warn!("No files matching {p}");
+                }
+
+                for f in files.iter().filter(|f| !f.is_dir) {
+                    c.delete(&f.path).await?;
+                    if !json {
+                        println!("Deleted {}", f.path);
+                    }
+
+                    results.push(Transfer {
+                        remote: f.path.clone(),
+                        local: None,
+                        bytes: f.size,
+                    });
+                }
+            }
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&results)?);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Execute timelapse commands
+async fn run_timelapse(
+    c: &mut FileClient,
+    cmd: TimelapseCommand,
+    json: bool,
+) -> anyhow::Result<()> {
+    match cmd {
+        TimelapseCommand::Ls => {
+            let files: Vec<_> = c
+                .list(TIMELAPSE_DIR)
+                .await?
+                .into_iter()
+                .filter(|f| !f.is_dir)
+                .collect();
+            print_files(&files, json)?;
+        }
+        TimelapseCommand::Fetch { pattern, out } => {
+            let path = format!("{TIMELAPSE_DIR}/{pattern}");
+            let results = download_matches(c, &path, &out, json).await?;
+
+            print_transfers(&results, json)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Check whether a path contains glob pattern characters
+fn has_glob(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+/// Resolve a remote path or glob pattern to matching entries
+async fn remote_matches(c: &mut FileClient, path: &str) -> anyhow::Result<Vec<FileEntry>> {
+    let (dir, name) = match path.rsplit_once('/') {
+        Some(("", name)) => ("/", name),
+        Some((dir, name)) => (dir, name),
+        None => ("/", path),
+    };
+
+    let pattern = match has_glob(name) {
+        true => Pattern::new(name)?,
+        false => Pattern::new(&Pattern::escape(name))?,
+    };
+
+    let files = c.list(dir).await?;
+
+    Ok(files
+        .into_iter()
+        .filter(|f| pattern.matches(&f.name))
+        .collect())
+}
+
+/// Download all files matching a remote path or glob pattern
+async fn download_matches(
+    c: &mut FileClient,
+    path: &str,
+    out: &std::path::Path,
+    json: bool,
+) -> anyhow::Result<Vec<Transfer>> {
+    let files = remote_matches(c, path).await?;
+    if files.is_empty() {
+        warn!("No files matching {path}");
+    }
+
+    let mut results = vec![];
+
+    for f in files.iter().filter(|f| !f.is_dir) {
+        let local = out.join(&f.name);
+
+        let bytes = c
+            .download_file(&f.path, &local, progress(&f.path, json))
+            .await?;
+        print_done(&f.path, json);
+
+        results.push(Transfer {
+            remote: f.path.clone(),
+            local: Some(local.display().to_string()),
+            bytes,
+        });
+    }
+
+    Ok(results)
+}
+
+/// Print a file listing
+fn print_files(files: &[FileEntry], json: bool) -> anyhow::Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(files)?);
+        return Ok(());
+    }
+
+    for f in files {
+        let suffix = if f.is_dir { "/" } else { "" };
+        println!("{:>12}  {:<12}  {}{suffix}", f.size, f.modified, f.path);
+    }
+
+    Ok(())
+}
+
+/// Print transfer results (JSON mode only, progress is shown otherwise)
+fn print_transfers(results: &[Transfer], json: bool) -> anyhow::Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(results)?);
+    }
+
+    Ok(())
+}
+
+/// Build a progress callback, writing to stderr unless JSON output is enabled
+fn progress(name: &str, json: bool) -> impl FnMut(Progress) {
+    let name = name.to_string();
+
+    move |p: Progress| {
+        if json {
+            return;
+        }
+
+        match p.total {
+            Some(t) if t > 0 => eprint!("\r{name}: {}%", p.transferred * 100 / t),
+            _ => eprint!("\r{name}: {} bytes", p.transferred),
+        }
+    }
+}
+
+/// Finish a progress line
+fn print_done(name: &str, json: bool) {
+    if !json {
+        eprintln!("\r{name}: done");
+    }
+}
@@ -0,0 +1,109 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Bambu printer models
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, displaydoc::Display)]
+pub enum Model {
+    /// X1 Carbon
+    X1C,
+    /// X1
+    X1,
+    /// X1E
+    X1E,
+    /// P1P
+    P1P,
+    /// P1S
+    P1S,
+    /// A1
+    A1,
+    /// A1 mini
+    A1Mini,
+}
+
+impl Model {
+    /// Detect the printer model from the serial number prefix
+    pub fn from_serial(serial: &str) -> Option<Self> {
+        let m = match serial.get(..3)? {
+            "00M" => Self::X1C,
+            "00W" => Self::X1,
+            "03W" => Self::X1E,
+            "01S" => Self::P1P,
+            "01P" => Self::P1S,
+            "039" => Self::A1,
+            "030" => Self::A1Mini,
+            _ => return None,
+        };
+
+        Some(m)
+    }
+
+    /// Detect the printer model from a slicer printer model id (e.g. `BL-P001`)
+    pub fn from_model_id(id: &str) -> Option<Self> {
+        let m = match id {
+            "BL-P001" => Self::X1C,
+            "BL-P002" => Self::X1,
+            "C13" => Self::X1E,
+            "C11" => Self::P1P,
+            "C12" => Self::P1S,
+            "N2S" => Self::A1,
+            "N1" => Self::A1Mini,
+            _ => return None,
+        };
+
+        Some(m)
+    }
+
+    /// Maximum nozzle temperature (°C)
+    pub fn max_nozzle_temp(&self) -> f32 {
+        match self {
+            Self::X1E => 320.0,
+            _ => 300.0,
+        }
+    }
+
+    /// Maximum bed temperature (°C)
+    pub fn max_bed_temp(&self) -> f32 {
+        match self {
+            Self::X1E => 120.0,
+            Self::X1C | Self::X1 => 110.0,
+            Self::A1Mini => 80.0,
+            _ => 100.0,
+        }
+    }
+
+    /// Build plate size, width (X) by depth (Y) (mm)
+    pub fn bed_size(&self) -> (f32, f32) {
+        match self {
+            Self::A1Mini => (180.0, 180.0),
+            _ => (256.0, 256.0),
+        }
+    }
+
+    /// Maximum chamber temperature (°C), for models with an actively heated chamber
+    pub fn max_chamber_temp(&self) -> Option<f32> {
+        match self {
+            Self::X1E => Some(60.0),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Model {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let m = match s.to_lowercase().replace(['-', '_', ' '], "").as_str() {
+            "x1c" | "x1carbon" => Self::X1C,
+            "x1" => Self::X1,
+            "x1e" => Self::X1E,
+            "p1p" => Self::P1P,
+            "p1s" => Self::P1S,
+            "a1" => Self::A1,
+            "a1mini" => Self::A1Mini,
+            _ => return Err(format!("unknown printer model: {s}")),
+        };
+
+        Ok(m)
+    }
+}
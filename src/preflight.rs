@@ -0,0 +1,345 @@
+//! Pre-print checks, run against the printer state before sending a `project_file` request
+
+use crate::{
+    ams::check_mapping,
+    job::{PrintJob, EXTERNAL_TRAY},
+    threemf::Plate,
+    types::{command::BedType, GcodeState, HmsLevel, PrintValue},
+};
+
+/// Preflight check categories
+#[derive(Clone, Copy, PartialEq, Eq, Debug, displaydoc::Display)]
+pub enum Check {
+    /// job
+    Job,
+    /// printer state
+    State,
+    /// printer model
+    Model,
+    /// build plate
+    Plate,
+    /// nozzle
+    Nozzle,
+    /// bed type
+    BedType,
+    /// filament
+    Filament,
+    /// storage
+    Storage,
+    /// HMS
+    Hms,
+}
+
+/// Preflight issue severity
+#[derive(Clone, Copy, PartialEq, Eq, Debug, displaydoc::Display)]
+pub enum Severity {
+    /// warning
+    Warning,
+    /// blocker
+    Blocker,
+}
+
+/// Preflight check result
+#[derive(Clone, PartialEq, Debug)]
+pub struct PreflightIssue {
+    pub check: Check,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl std::fmt::Display for PreflightIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({}): {}", self.severity, self.check, self.message)
+    }
+}
+
+/// Preflight report
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct PreflightReport {
+    pub issues: Vec<PreflightIssue>,
+}
+
+impl PreflightReport {
+    /// Check whether the job can be started
+    pub fn is_ok(&self) -> bool {
+        self.blockers().next().is_none()
+    }
+
+    /// Issues preventing the job from starting
+    pub fn blockers(&self) -> impl Iterator<Item = &PreflightIssue> {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == Severity::Blocker)
+    }
+
+    /// Issues to be reviewed before starting the job
+    pub fn warnings(&self) -> impl Iterator<Item = &PreflightIssue> {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == Severity::Warning)
+    }
+
+    pub(crate) fn push(&mut self, check: Check, severity: Severity, message: impl ToString) {
+        self.issues.push(PreflightIssue {
+            check,
+            severity,
+            message: message.to_string(),
+        });
+    }
+}
+
+/// Check a print job for a sliced plate against the current printer status
+pub fn preflight(job: &PrintJob, plate: &Plate, status: &PrintValue) -> PreflightReport {
+    use Severity::*;
+
+    let mut r = PreflightReport::default();
+
+    if let Err(e) = job.validate() {
+        r.push(Check::Job, Blocker, e);
+    }
+    if !plate.sliced {
+        r.push(
+            Check::Job,
+            Blocker,
+            format!("plate {} is not sliced", plate.index),
+        );
+    }
+
+    // Printer must be idle
+    match status.gcode_state {
+        Some(
+            s @ (GcodeState::Prepare
+            | GcodeState::Slicing
+            | GcodeState::Running
+            | GcodeState::Pause),
+        ) => r.push(Check::State, Blocker, format!("printer busy ({s})")),
+        Some(GcodeState::Unknown) | None => r.push(Check::State, Warning, "printer state unknown"),
+        _ => (),
+    }
+
+    // Plate must be confirmed clear, or checked by the camera
+    if !job.plate_clear {
+        match status
+            .xcam
+            .as_ref()
+            .and_then(|x| x.buildplate_marker_detector)
+        {
+            Some(true) => r.push(
+                Check::Plate,
+                Warning,
+                "plate not confirmed clear, relying on build plate detection",
+            ),
+            _ => r.push(Check::Plate, Blocker, "plate not confirmed clear"),
+        }
+    }
+
+    // Nozzle must match the sliced diameter
+    let nozzle = status
+        .nozzle_diameter
+        .as_ref()
+        .and_then(|d| d.parse::<f32>().ok());
+    match (plate.nozzle_diameter, nozzle) {
+        (Some(s), Some(n)) if (s - n).abs() > 0.01 => r.push(
+            Check::Nozzle,
+            Blocker,
+            format!("sliced for {s}mm nozzle, printer has {n}mm"),
+        ),
+        (Some(_), Some(_)) => (),
+        _ => r.push(Check::Nozzle, Warning, "nozzle diameter unknown"),
+    }
+
+    // Bed type must match the sliced plate type
+    if let Some(s) = plate.bed_type {
+        let b = job.bed_type;
+        if b != BedType::Auto && b != s {
+            r.push(
+                Check::BedType,
+                Blocker,
+                format!("sliced for {s:?}, job uses {b:?}"),
+            );
+        }
+    }
+
+    // Filaments must be loaded with enough remaining
+    let mapping = match &job.ams_mapping {
+        Some(m) => m.clone(),
+        None => vec![EXTERNAL_TRAY; plate.filaments.iter().map(|f| f.id).max().unwrap_or(0)],
+    };
+    let m = check_mapping(&plate.filaments, &mapping, status);
+    for (f, i) in m.issues() {
+        let severity = match i.is_blocking() {
+            true => Blocker,
+            false => Warning,
+        };
+        r.push(
+            Check::Filament,
+            severity,
+            format!("filament {} ({}): {i}", f.id, f.filament_type),
+        );
+    }
+
+    // SD card must be present
+    match status.sdcard {
+        Some(true) => (),
+        Some(false) => r.push(Check::Storage, Blocker, "no SD card inserted"),
+        None => r.push(Check::Storage, Warning, "SD card state unknown"),
+    }
+
+    // No active HMS errors
+    for h in status.hms.iter().flatten() {
+        let severity = match h.level() {
+            HmsLevel::Info => Warning,
+            _ => Blocker,
+        };
+        r.push(
+            Check::Hms,
+            severity,
+            format!(
+                "{} {}: {}",
+                h.level(),
+                h.error_code(),
+                h.message().unwrap_or("unknown error")
+            ),
+        );
+    }
+
+    r
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::{
+        threemf::Filament,
+        types::{Ams, AmsInfo, Hms, Tray, TrayInfo, XCam},
+    };
+
+    fn plate() -> Plate {
+        Plate {
+            index: 1,
+            sliced: true,
+            nozzle_diameter: Some(0.4),
+            bed_type: Some(BedType::TexturedPlate),
+            filaments: vec![Filament {
+                id: 1,
+                filament_type: "PLA".to_string(),
+                color: "#FFFFFF".to_string(),
+                used_g: 20.0,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn status() -> PrintValue {
+        PrintValue {
+            gcode_state: Some(GcodeState::Idle),
+            nozzle_diameter: Some("0.4".to_string()),
+            sdcard: Some(true),
+            ams: Some(Ams {
+                ams: vec![AmsInfo {
+                    id: "0".to_string(),
+                    tray: vec![Tray {
+                        id: "0".to_string(),
+                        info: TrayInfo {
+                            tray_type: "PLA".to_string(),
+                            tray_color: "FFFFFFFF".to_string(),
+                            tray_weight: "1000".to_string(),
+                            remain: 50,
+                            ..Default::default()
+                        },
+                    }],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn job() -> PrintJob {
+        PrintJob {
+            plate_clear: true,
+            ams_mapping: Some(vec![0]),
+            ..PrintJob::new("model.gcode.3mf")
+        }
+    }
+
+    #[test]
+    fn preflight_ok() {
+        let r = preflight(&job(), &plate(), &status());
+        assert_eq!(r.issues, vec![]);
+        assert!(r.is_ok());
+    }
+
+    #[test]
+    fn preflight_blockers() {
+        let status = PrintValue {
+            gcode_state: Some(GcodeState::Running),
+            nozzle_diameter: Some("0.6".to_string()),
+            sdcard: Some(false),
+            hms: Some(vec![Hms {
+                attr: 0x0300_1000,
+                code: 0x0002_0001,
+            }]),
+            ..status()
+        };
+        let job = PrintJob {
+            plate_clear: false,
+            bed_type: BedType::CoolPlate,
+            ams_mapping: Some(vec![1]),
+            ..job()
+        };
+
+        let r = preflight(&job, &plate(), &status);
+
+        let checks: Vec<_> = r.blockers().map(|i| i.check).collect();
+        assert_eq!(
+            checks,
+            vec![
+                Check::State,
+                Check::Plate,
+                Check::Nozzle,
+                Check::BedType,
+                Check::Filament,
+                Check::Storage,
+                Check::Hms
+            ]
+        );
+        assert!(!r.is_ok());
+    }
+
+    #[test]
+    fn preflight_unsliced() {
+        let unsliced = Plate {
+            sliced: false,
+            ..plate()
+        };
+        let r = preflight(&job(), &unsliced, &status());
+        let checks: Vec<_> = r.blockers().map(|i| i.check).collect();
+        assert_eq!(checks, vec![Check::Job]);
+    }
+
+    #[test]
+    fn preflight_warnings() {
+        let status = PrintValue {
+            sdcard: None,
+            xcam: Some(XCam {
+                buildplate_marker_detector: Some(true),
+                ..Default::default()
+            }),
+            ..status()
+        };
+        let job = PrintJob {
+            plate_clear: false,
+            ..job()
+        };
+
+        let r = preflight(&job, &plate(), &status);
+
+        let checks: Vec<_> = r.warnings().map(|i| i.check).collect();
+        assert_eq!(checks, vec![Check::Plate, Check::Storage]);
+        assert!(r.is_ok());
+    }
+}
@@ -1,21 +1,58 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
 use futures::StreamExt;
-use paho_mqtt::{AsyncClient, ConnectOptionsBuilder, CreateOptionsBuilder, SslOptionsBuilder};
+use paho_mqtt::{
+    AsyncClient, ConnectOptionsBuilder, CreateOptionsBuilder, Message, SslOptionsBuilder,
+};
 use rustls::client::ServerCertVerifier;
-use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::{
+    mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    oneshot,
+};
 use tracing::{debug, error, trace};
 
-use crate::{ConnectOpts, Error};
+use crate::{
+    control::{fan_gcode, temperature_gcode, Fan, Heater, Motion, TempLimits, MIN_EXTRUDE_TEMP},
+    job::PrintJob,
+    model::Model,
+    preflight::{preflight, Check, PreflightReport, Severity},
+    threemf::Plate,
+    types::{
+        command::{Command, PrintCommand, PushingCommand},
+        GcodeState, PrintValue, Report, SpeedProfile,
+    },
+    ConnectOpts, Error,
+};
+
+/// Timeout for printer responses
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Bambu printer handle
 #[derive(Clone)]
 pub struct Printer {
     opts: ConnectOpts,
     tx: UnboundedSender<Commands>,
+    state: Arc<Mutex<State>>,
+    sequence_id: Arc<AtomicUsize>,
 }
 
-#[derive(Clone, Debug)]
+/// Printer state, updated from incoming reports
+#[derive(Clone, Debug, Default)]
+struct State {
+    serial: Option<String>,
+    status: PrintValue,
+}
+
+#[derive(Debug)]
 pub enum Commands {
     Subscribe(PrinterSender),
+    Publish(String, String, oneshot::Sender<Result<(), Error>>),
     Disconnect,
 }
 
@@ -64,12 +101,17 @@ impl Printer {
         debug!("Subscribing to topic");
         client.subscribe("#", 2).await?;
 
+        let state = Arc::new(Mutex::new(State {
+            serial: opts.serial.clone(),
+            ..Default::default()
+        }));
+        let task_state = state.clone();
+
         // Start listener
         let _h = tokio::task::spawn(async move {
             debug!("Start connection task");
 
             let mut listeners: Vec<PrinterSender> = vec![];
-            let _ = client;
 
             loop {
                 tokio::select!(
@@ -87,6 +129,8 @@ impl Printer {
                                     }
                                 };
 
+                                update_state(&task_state, &topic, &payload);
+
                                 listeners.retain(|tx| {
                                     tx.send((topic.clone(), payload.clone())).is_ok()
                                 });
@@ -98,6 +142,12 @@ impl Printer {
                     c = rx.recv() => {
                         match c {
                             Some(Commands::Subscribe(tx)) => listeners.push(tx),
+                            Some(Commands::Publish(topic, payload, done)) => {
+                                trace!("tx {topic}: {payload}");
+
+                                let r = client.publish(Message::new(topic, payload, 1)).await;
+                                let _ = done.send(r.map_err(Error::from));
+                            },
                             Some(Commands::Disconnect) => break,
                             None => (),
                         }
@@ -112,7 +162,12 @@ impl Printer {
             }
         });
 
-        Ok(Self { opts, tx })
+        Ok(Self {
+            opts,
+            tx,
+            state,
+            sequence_id: Arc::new(AtomicUsize::new(0)),
+        })
     }
 
     /// Fetch listen channel for receiving events
@@ -128,6 +183,214 @@ impl Printer {
         Ok(rx)
     }
 
+    /// Fetch the printer serial, if known
+    pub fn serial(&self) -> Option<String> {
+        self.state.lock().unwrap().serial.clone()
+    }
+
+    /// Fetch the printer model, detected from the serial number
+    pub fn model(&self) -> Option<Model> {
+        self.serial().as_deref().and_then(Model::from_serial)
+    }
+
+    /// Fetch the latest printer status, merged from received reports
+    pub fn status(&self) -> PrintValue {
+        self.state.lock().unwrap().status.clone()
+    }
+
+    /// Fetch the next request sequence id
+    pub fn sequence_id(&self) -> usize {
+        self.sequence_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Send a request to the printer
+    pub async fn request(&self, command: Command) -> Result<(), Error> {
+        let serial = self.serial().ok_or(Error::UnknownSerial)?;
+
+        let topic = format!("device/{serial}/request");
+        let payload = serde_json::to_string(&command)?;
+
+        let (done_tx, done_rx) = oneshot::channel();
+        self.tx
+            .send(Commands::Publish(topic, payload, done_tx))
+            .map_err(|_| Error::SendError)?;
+
+        done_rx.await.map_err(|_| Error::SendError)?
+    }
+
+    /// Request a full status report, returning the updated printer status
+    ///
+    /// If the serial is not yet known this waits for the first report to detect it.
+    pub async fn refresh(&self) -> Result<PrintValue, Error> {
+        let mut rx = self.listen()?;
+
+        let wait = async {
+            while self.serial().is_none() {
+                rx.recv().await.ok_or(Error::SendError)?;
+            }
+
+            self.request(Command::pushing(
+                self.sequence_id(),
+                PushingCommand::Pushall,
+            ))
+            .await?;
+
+            while let Some((_topic, data)) = rx.recv().await {
+                if let Ok(Report::Print { .. }) = serde_json::from_str::<Report>(&data) {
+                    return Ok(self.status());
+                }
+            }
+
+            Err(Error::SendError)
+        };
+
+        tokio::time::timeout(RESPONSE_TIMEOUT, wait)
+            .await
+            .map_err(|_| Error::Timeout)?
+    }
+
+    /// Send a print command to the printer
+    pub async fn print_command(&self, command: PrintCommand) -> Result<(), Error> {
+        self.request(Command::print(self.sequence_id(), command))
+            .await
+    }
+
+    /// Execute G-code on the printer, lines separated by `\n`
+    pub async fn gcode(&self, gcode: &str) -> Result<(), Error> {
+        self.print_command(PrintCommand::GcodeLine {
+            param: format!("{}\n", gcode.trim_end()),
+        })
+        .await
+    }
+
+    /// Set a heater target temperature (°C)
+    ///
+    /// Targets are checked against the model limits and, for the nozzle,
+    /// the limits of the active AMS tray or external spool.
+    pub async fn set_temperature(&self, heater: Heater, target: f32) -> Result<(), Error> {
+        let model = self.model().ok_or(Error::UnknownModel)?;
+
+        let mut limits = TempLimits::for_model(model);
+        if let Some(tray) = self.status().active_tray() {
+            limits = limits.with_tray(tray);
+        }
+
+        limits.check(heater, target)?;
+
+        self.gcode(&temperature_gcode(heater, target)).await
+    }
+
+    /// Set the print speed profile
+    pub async fn set_speed(&self, profile: SpeedProfile) -> Result<(), Error> {
+        self.print_command(PrintCommand::PrintSpeed {
+            param: (profile as u8).to_string(),
+        })
+        .await
+    }
+
+    /// Set a fan speed (%)
+    pub async fn set_fan(&self, fan: Fan, percent: u8) -> Result<(), Error> {
+        self.gcode(&fan_gcode(fan, percent)?).await
+    }
+
+    /// Execute a maintenance motion, refused unless the printer is idle
+    ///
+    /// Extrusion is also refused below [MIN_EXTRUDE_TEMP].
+    pub async fn motion(&self, motion: &Motion) -> Result<(), Error> {
+        let status = self.check_idle().await?;
+
+        if let Motion::Extrude { .. } = motion {
+            let temp = status.nozzle_temper.ok_or(Error::UnknownStatus)?;
+            if temp < MIN_EXTRUDE_TEMP {
+                return Err(Error::ColdExtrusion {
+                    temp,
+                    min: MIN_EXTRUDE_TEMP,
+                });
+            }
+        }
+
+        self.gcode(&motion.gcode()?).await
+    }
+
+    /// Fetch the printer status, refreshing if no state has been reported yet
+    async fn current_status(&self) -> Result<PrintValue, Error> {
+        match self.status() {
+            s if s.gcode_state.is_some() => Ok(s),
+            _ => self.refresh().await,
+        }
+    }
+
+    /// Check the printer is not preparing or printing, returning the current status
+    async fn check_idle(&self) -> Result<PrintValue, Error> {
+        let status = self.current_status().await?;
+
+        match status.gcode_state {
+            Some(
+                s @ (GcodeState::Prepare
+                | GcodeState::Slicing
+                | GcodeState::Running
+                | GcodeState::Pause
+                | GcodeState::Unknown),
+            ) => Err(Error::PrinterBusy(s)),
+            Some(_) => Ok(status),
+            None => Err(Error::UnknownStatus),
+        }
+    }
+
+    /// Start printing a project file from printer storage, refused unless the printer is idle
+    pub async fn start_print(&self, job: &PrintJob) -> Result<(), Error> {
+        job.validate()?;
+        self.check_idle().await?;
+
+        self.print_command(PrintCommand::ProjectFile(Box::new(job.project_file())))
+            .await
+    }
+
+    /// Run calibrations, `option` is a bitmask of `CALIBRATE_*` flags
+    ///
+    /// Refused unless the printer is idle.
+    pub async fn calibrate(&self, option: u32) -> Result<(), Error> {
+        self.check_idle().await?;
+
+        self.print_command(PrintCommand::Calibration { option })
+            .await
+    }
+
+    /// Run preflight checks for a job, see [preflight]
+    pub fn preflight(&self, job: &PrintJob, plate: &Plate) -> PreflightReport {
+        let mut r = preflight(job, plate, &self.status());
+
+        match (plate.model, self.model()) {
+            (Some(s), Some(m)) if s != m => r.push(
+                Check::Model,
+                Severity::Blocker,
+                format!("sliced for {s}, printer is {m}"),
+            ),
+            (Some(_), Some(_)) => (),
+            _ => r.push(Check::Model, Severity::Warning, "printer model unknown"),
+        }
+
+        r
+    }
+
+    /// Skip objects in the current print, by sliced object `identify_id`
+    pub async fn skip_objects(&self, ids: &[u64]) -> Result<(), Error> {
+        if ids.is_empty() {
+            return Err(Error::InvalidJob("no objects to skip".to_string()));
+        }
+
+        match self.status().gcode_state {
+            Some(GcodeState::Running | GcodeState::Pause) => (),
+            Some(s) => return Err(Error::NotPrinting(s)),
+            None => return Err(Error::NotPrinting(GcodeState::Unknown)),
+        }
+
+        self.print_command(PrintCommand::SkipObjects {
+            obj_list: ids.to_vec(),
+        })
+        .await
+    }
+
     /// Disconnect client
     pub async fn disconnect(self) -> Result<(), Error> {
         self.tx
@@ -138,6 +401,26 @@ impl Printer {
     }
 }
 
+/// Update printer state from an incoming message
+fn update_state(state: &Mutex<State>, topic: &str, payload: &str) {
+    let mut state = state.lock().unwrap();
+
+    // Learn serial from report topics (`device/{serial}/report`)
+    if state.serial.is_none() {
+        if let Some(serial) = topic
+            .strip_prefix("device/")
+            .and_then(|t| t.strip_suffix("/report"))
+        {
+            debug!("Detected printer serial: {serial}");
+            state.serial = Some(serial.to_string());
+        }
+    }
+
+    if let Ok(Report::Print { value, .. }) = serde_json::from_str::<Report>(payload) {
+        state.status.update(&value);
+    }
+}
+
 impl std::hash::Hash for Printer {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.opts.hostname.hash(state);
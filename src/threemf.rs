@@ -0,0 +1,513 @@
+//! Bambu sliced 3MF (`.gcode.3mf`) print package reader
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Seek},
+    path::Path,
+};
+
+use quick_xml::events::{BytesStart, Event};
+use serde::{Deserialize, Serialize};
+use zip::ZipArchive;
+
+use crate::{model::Model, types::command::BedType, Error};
+
+/// Slicer plate information
+const SLICE_INFO: &str = "Metadata/slice_info.config";
+
+/// Slicer project settings
+const PROJECT_SETTINGS: &str = "Metadata/project_settings.config";
+
+/// Sliced print package
+pub struct ThreeMf<R = File> {
+    archive: ZipArchive<R>,
+    /// Slicer version, from the package header
+    pub client_version: Option<String>,
+    /// Sliced plates
+    pub plates: Vec<Plate>,
+}
+
+/// Sliced plate metadata
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Plate {
+    /// Plate index (1-based)
+    pub index: usize,
+    /// Plate G-code path within the package
+    pub gcode: String,
+    /// Plate G-code is included in the package, unsliced plates can not be printed
+    pub sliced: bool,
+    /// MD5 of the plate G-code, if included
+    pub gcode_md5: Option<String>,
+    /// Plate thumbnail path within the package
+    pub thumbnail: Option<String>,
+    /// Estimated print time (s)
+    pub prediction: Option<u64>,
+    /// Estimated filament weight (g)
+    pub weight: Option<f32>,
+    /// Slicer printer model id (e.g. `BL-P001`)
+    pub printer_model_id: Option<String>,
+    /// Target printer model
+    pub model: Option<Model>,
+    /// Nozzle diameter (mm)
+    pub nozzle_diameter: Option<f32>,
+    /// Build plate type
+    pub bed_type: Option<BedType>,
+    /// Filaments used by the plate
+    pub filaments: Vec<Filament>,
+    /// Objects on the plate
+    pub objects: Vec<PlateObject>,
+}
+
+/// Sliced filament
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Filament {
+    /// Slicer filament id (1-based)
+    pub id: usize,
+    /// Filament preset id (e.g. `GFA00`)
+    pub tray_info_idx: String,
+    /// Filament type (e.g. `PLA`)
+    pub filament_type: String,
+    /// Filament colour (`#RRGGBB`)
+    pub color: String,
+    /// Estimated usage (m)
+    pub used_m: f32,
+    /// Estimated usage (g)
+    pub used_g: f32,
+}
+
+/// Object on a sliced plate
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct PlateObject {
+    /// Object id used for `skip_objects`
+    pub identify_id: u64,
+    /// Object name
+    pub name: String,
+    /// Object skipped in the slicer
+    pub skipped: bool,
+}
+
+impl ThreeMf<File> {
+    /// Open a sliced 3MF file
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_reader(File::open(path)?)
+    }
+}
+
+impl<R: Read + Seek> ThreeMf<R> {
+    /// Load a sliced 3MF from a reader
+    pub fn from_reader(r: R) -> Result<Self, Error> {
+        let mut archive = ZipArchive::new(r)?;
+
+        let info = read_string(&mut archive, SLICE_INFO)?;
+        let (client_version, mut plates) = parse_slice_info(&info)?;
+
+        if plates.is_empty() {
+            return Err(Error::ThreeMf("no sliced plates found".to_string()));
+        }
+
+        // Fallback to project settings for the nozzle diameter, and load the bed type
+        let settings: Option<serde_json::Value> = read_string(&mut archive, PROJECT_SETTINGS)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok());
+        let settings_nozzle = settings.as_ref().and_then(parse_nozzle_diameter);
+        let bed_type = settings.as_ref().and_then(parse_bed_type);
+
+        let names: Vec<String> = archive.file_names().map(|n| n.to_string()).collect();
+
+        for p in plates.iter_mut() {
+            p.gcode = format!("Metadata/plate_{}.gcode", p.index);
+            p.sliced = names.contains(&p.gcode);
+
+            let md5 = format!("{}.md5", p.gcode);
+            if p.sliced && names.contains(&md5) {
+                p.gcode_md5 = read_string(&mut archive, &md5)
+                    .ok()
+                    .map(|s| s.trim().to_string());
+            }
+
+            let thumbnail = format!("Metadata/plate_{}.png", p.index);
+            if names.contains(&thumbnail) {
+                p.thumbnail = Some(thumbnail);
+            }
+
+            p.model = p.printer_model_id.as_deref().and_then(Model::from_model_id);
+            p.nozzle_diameter = p.nozzle_diameter.or(settings_nozzle);
+            p.bed_type = bed_type;
+        }
+
+        // Projects may be partially sliced, but at least one plate must be printable
+        if !plates.iter().any(|p| p.sliced) {
+            return Err(Error::ThreeMf("no plate G-code found".to_string()));
+        }
+
+        Ok(Self {
+            archive,
+            client_version,
+            plates,
+        })
+    }
+
+    /// Fetch plate metadata by index (1-based)
+    pub fn plate(&self, index: usize) -> Option<&Plate> {
+        self.plates.iter().find(|p| p.index == index)
+    }
+
+    /// Read the G-code for a plate
+    pub fn gcode(&mut self, index: usize) -> Result<String, Error> {
+        let p = self
+            .plate(index)
+            .ok_or(Error::ThreeMf(format!("no plate with index {index}")))?;
+        if !p.sliced {
+            return Err(Error::ThreeMf(format!("plate {index} is not sliced")));
+        }
+        let path = p.gcode.clone();
+
+        read_string(&mut self.archive, &path)
+    }
+
+    /// Read the PNG thumbnail for a plate
+    pub fn thumbnail(&mut self, index: usize) -> Result<Vec<u8>, Error> {
+        let path = self
+            .plate(index)
+            .and_then(|p| p.thumbnail.clone())
+            .ok_or(Error::ThreeMf(format!("no thumbnail for plate {index}")))?;
+
+        let mut buff = vec![];
+        self.archive.by_name(&path)?.read_to_end(&mut buff)?;
+
+        Ok(buff)
+    }
+}
+
+impl Plate {
+    /// Check the plate was sliced for the provided printer model and nozzle
+    pub fn check_printer(&self, model: Model, nozzle_diameter: f32) -> Result<(), Error> {
+        match self.model {
+            Some(m) if m != model => {
+                return Err(Error::IncompatibleJob(format!(
+                    "sliced for {m}, printer is {model}"
+                )));
+            }
+            None => {
+                return Err(Error::IncompatibleJob(format!(
+                    "unknown target printer {}",
+                    self.printer_model_id.as_deref().unwrap_or("(none)")
+                )))
+            }
+            _ => (),
+        }
+
+        match self.nozzle_diameter {
+            Some(d) if (d - nozzle_diameter).abs() > 0.01 => Err(Error::IncompatibleJob(format!(
+                "sliced for {d}mm nozzle, printer has {nozzle_diameter}mm"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Resolve objects by `identify_id` or name to ids for `skip_objects`
+    ///
+    /// Names shared by multiple objects (e.g. copies of one model) are rejected as ambiguous.
+    pub fn object_ids(&self, objects: &[impl AsRef<str>]) -> Result<Vec<u64>, Error> {
+        objects
+            .iter()
+            .map(|o| {
+                let o = o.as_ref();
+
+                // Ids are unique, so take precedence over names
+                if let Some(p) = self.objects.iter().find(|p| o.parse() == Ok(p.identify_id)) {
+                    return Ok(p.identify_id);
+                }
+
+                let ids: Vec<_> = self
+                    .objects
+                    .iter()
+                    .filter(|p| p.name == o)
+                    .map(|p| p.identify_id)
+                    .collect();
+
+                match ids[..] {
+                    [id] => Ok(id),
+                    [] => Err(Error::InvalidJob(format!(
+                        "no object {o} on plate {}",
+                        self.index
+                    ))),
+                    _ => Err(Error::InvalidJob(format!(
+                        "ambiguous object name {o} matches ids {ids:?}, use identify_id"
+                    ))),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Read a file from the archive as a string
+fn read_string<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<String, Error> {
+    let mut s = String::new();
+    archive.by_name(name)?.read_to_string(&mut s)?;
+    Ok(s)
+}
+
+/// Collect element attributes
+fn attributes(e: &BytesStart) -> Result<HashMap<String, String>, Error> {
+    let mut attrs = HashMap::new();
+
+    for a in e.attributes() {
+        let a = a.map_err(|e| Error::ThreeMf(e.to_string()))?;
+        let k = String::from_utf8_lossy(a.key.as_ref()).to_string();
+        let v = a
+            .unescape_value()
+            .map_err(|e| Error::ThreeMf(e.to_string()))?;
+        attrs.insert(k, v.to_string());
+    }
+
+    Ok(attrs)
+}
+
+/// Parse `slice_info.config`, returning the client version and plates
+fn parse_slice_info(s: &str) -> Result<(Option<String>, Vec<Plate>), Error> {
+    let mut reader = quick_xml::Reader::from_str(s);
+    reader.trim_text(true);
+
+    let mut client_version = None;
+    let mut plates = vec![];
+    let mut plate: Option<Plate> = None;
+
+    loop {
+        let e = match reader
+            .read_event()
+            .map_err(|e| Error::ThreeMf(format!("{e} at {}", reader.buffer_position())))?
+        {
+            Event::Eof => break,
+            Event::End(e) if e.name().as_ref() == b"plate" => {
+                plates.extend(plate.take());
+                continue;
+            }
+            Event::Start(e) if e.name().as_ref() == b"plate" => {
+                plate = Some(Plate::default());
+                continue;
+            }
+            Event::Start(e) | Event::Empty(e) => e,
+            _ => continue,
+        };
+
+        let a = attributes(&e)?;
+        let get = |k: &str| a.get(k).map(|v| v.as_str()).unwrap_or_default();
+
+        match (e.name().as_ref(), plate.as_mut()) {
+            (b"header_item", _) if get("key") == "X-BBL-Client-Version" => {
+                client_version = Some(get("value").to_string());
+            }
+            (b"metadata", Some(p)) => {
+                let v = get("value");
+                match get("key") {
+                    "index" => p.index = v.parse().unwrap_or_default(),
+                    "prediction" => p.prediction = v.parse().ok(),
+                    "weight" => p.weight = v.parse().ok(),
+                    "printer_model_id" => p.printer_model_id = Some(v.to_string()),
+                    "nozzle_diameters" => {
+                        p.nozzle_diameter = v.split(',').next().and_then(|v| v.parse().ok())
+                    }
+                    _ => (),
+                }
+            }
+            (b"filament", Some(p)) => p.filaments.push(Filament {
+                id: get("id").parse().unwrap_or_default(),
+                tray_info_idx: get("tray_info_idx").to_string(),
+                filament_type: get("type").to_string(),
+                color: get("color").to_string(),
+                used_m: get("used_m").parse().unwrap_or_default(),
+                used_g: get("used_g").parse().unwrap_or_default(),
+            }),
+            (b"object", Some(p)) => p.objects.push(PlateObject {
+                identify_id: get("identify_id").parse().unwrap_or_default(),
+                name: get("name").to_string(),
+                skipped: get("skipped") == "true",
+            }),
+            _ => (),
+        }
+    }
+
+    Ok((client_version, plates))
+}
+
+/// Parse the nozzle diameter from `project_settings.config`
+fn parse_nozzle_diameter(v: &serde_json::Value) -> Option<f32> {
+    match v.get("nozzle_diameter")? {
+        serde_json::Value::Array(a) => a.first()?.as_str()?.parse().ok(),
+        serde_json::Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Parse the build plate type from `project_settings.config`
+fn parse_bed_type(v: &serde_json::Value) -> Option<BedType> {
+    let t = match v.get("curr_bed_type")?.as_str()? {
+        "Cool Plate" => BedType::CoolPlate,
+        "Engineering Plate" => BedType::EngineeringPlate,
+        "High Temp Plate" => BedType::HighTempPlate,
+        "Textured PEI Plate" => BedType::TexturedPlate,
+        _ => return None,
+    };
+
+    Some(t)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::{Cursor, Write};
+
+    use zip::{write::FileOptions, ZipWriter};
+
+    use super::*;
+
+    const SLICE_INFO_XML: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<config>
+  <header>
+    <header_item key="X-BBL-Client-Type" value="slicer"/>
+    <header_item key="X-BBL-Client-Version" value="01.07.04.52"/>
+  </header>
+  <plate>
+    <metadata key="index" value="1"/>
+    <metadata key="printer_model_id" value="C12"/>
+    <metadata key="nozzle_diameters" value="0.4"/>
+    <metadata key="prediction" value="5263"/>
+    <metadata key="weight" value="46.42"/>
+    <object identify_id="139" name="bracket_left.stl" skipped="false" />
+    <object identify_id="183" name="bracket_right.stl" skipped="false" />
+    <filament id="1" tray_info_idx="GFA00" type="PLA" color="#FFFFFF" used_m="15.38" used_g="45.87" />
+    <filament id="3" tray_info_idx="GFL99" type="PETG" color="#0A2989" used_m="0.19" used_g="0.55" />
+  </plate>
+</config>
+"##;
+
+    fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut z = ZipWriter::new(Cursor::new(vec![]));
+        let o = FileOptions::default();
+
+        for (name, data) in files {
+            z.start_file(*name, o).unwrap();
+            z.write_all(data).unwrap();
+        }
+
+        z.finish().unwrap().into_inner()
+    }
+
+    fn package() -> Vec<u8> {
+        zip(&[
+            (SLICE_INFO, SLICE_INFO_XML.as_bytes()),
+            ("Metadata/plate_1.gcode", b"G28\n"),
+            ("Metadata/plate_1.gcode.md5", b"0123456789abcdef\n"),
+            ("Metadata/plate_1.png", &[0x89, b'P', b'N', b'G']),
+            (
+                PROJECT_SETTINGS,
+                br#"{"curr_bed_type": "Textured PEI Plate", "nozzle_diameter": ["0.6"]}"#,
+            ),
+        ])
+    }
+
+    #[test]
+    fn read_package() {
+        let mut p = ThreeMf::from_reader(Cursor::new(package())).unwrap();
+
+        assert_eq!(p.client_version.as_deref(), Some("01.07.04.52"));
+        assert_eq!(p.plates.len(), 1);
+
+        let plate = p.plate(1).unwrap().clone();
+        assert_eq!(plate.gcode, "Metadata/plate_1.gcode");
+        assert!(plate.sliced);
+        assert_eq!(plate.gcode_md5.as_deref(), Some("0123456789abcdef"));
+        assert_eq!(plate.thumbnail.as_deref(), Some("Metadata/plate_1.png"));
+        assert_eq!(plate.prediction, Some(5263));
+        assert_eq!(plate.weight, Some(46.42));
+        assert_eq!(plate.model, Some(Model::P1S));
+        assert_eq!(plate.nozzle_diameter, Some(0.4));
+        assert_eq!(plate.bed_type, Some(BedType::TexturedPlate));
+
+        assert_eq!(
+            plate.objects,
+            vec![
+                PlateObject {
+                    identify_id: 139,
+                    name: "bracket_left.stl".to_string(),
+                    skipped: false
+                },
+                PlateObject {
+                    identify_id: 183,
+                    name: "bracket_right.stl".to_string(),
+                    skipped: false
+                },
+            ]
+        );
+
+        assert_eq!(plate.filaments.len(), 2);
+        assert_eq!(plate.filaments[1].id, 3);
+        assert_eq!(plate.filaments[1].filament_type, "PETG");
+        assert_eq!(plate.filaments[1].color, "#0A2989");
+        assert_eq!(plate.filaments[0].used_g, 45.87);
+
+        assert_eq!(p.gcode(1).unwrap(), "G28\n");
+        assert_eq!(p.thumbnail(1).unwrap(), vec![0x89, b'P', b'N', b'G']);
+        assert!(p.gcode(2).is_err());
+    }
+
+    #[test]
+    fn partially_sliced() {
+        let info = SLICE_INFO_XML.replace(
+            "</config>",
+            r#"<plate><metadata key="index" value="2"/></plate></config>"#,
+        );
+
+        let mut p = ThreeMf::from_reader(Cursor::new(zip(&[
+            (SLICE_INFO, info.as_bytes()),
+            ("Metadata/plate_1.gcode", b"G28\n"),
+        ])))
+        .unwrap();
+
+        assert_eq!(p.plates.len(), 2);
+        assert!(!p.plate(2).unwrap().sliced);
+        assert_eq!(p.gcode(1).unwrap(), "G28\n");
+        assert!(matches!(p.gcode(2), Err(Error::ThreeMf(_))));
+
+        // Packages without any plate G-code are rejected
+        let r = ThreeMf::from_reader(Cursor::new(zip(&[(SLICE_INFO, info.as_bytes())])));
+        assert!(matches!(r, Err(Error::ThreeMf(_))));
+    }
+
+    #[test]
+    fn check_printer() {
+        let p = ThreeMf::from_reader(Cursor::new(package())).unwrap();
+        let plate = p.plate(1).unwrap();
+
+        assert!(plate.check_printer(Model::P1S, 0.4).is_ok());
+        assert!(plate.check_printer(Model::X1C, 0.4).is_err());
+        assert!(plate.check_printer(Model::P1S, 0.6).is_err());
+    }
+
+    #[test]
+    fn object_ids() {
+        let p = ThreeMf::from_reader(Cursor::new(package())).unwrap();
+        let plate = p.plate(1).unwrap();
+
+        assert_eq!(
+            plate.object_ids(&["bracket_right.stl", "139"]).unwrap(),
+            vec![183, 139]
+        );
+        assert!(plate.object_ids(&["140"]).is_err());
+
+        // Copies of a model share a name
+        let mut plate = plate.clone();
+        plate.objects.push(PlateObject {
+            identify_id: 201,
+            name: "bracket_right.stl".to_string(),
+            skipped: false,
+        });
+        assert!(matches!(
+            plate.object_ids(&["bracket_right.stl"]),
+            Err(Error::InvalidJob(_))
+        ));
+        assert_eq!(plate.object_ids(&["201", "139"]).unwrap(), vec![201, 139]);
+        assert_eq!(plate.object_ids(&["bracket_left.stl"]).unwrap(), vec![139]);
+    }
+}
@@ -4,57 +4,202 @@ use serde::{Deserialize, Serialize};
 #[serde(rename_all = "snake_case")]
 pub enum Command {
     System {
-        sequence_id: usize,
+        sequence_id: String,
+        #[serde(flatten)]
         command: SystemCommand,
     },
     Print {
-        sequence_id: usize,
+        sequence_id: String,
+        #[serde(flatten)]
         command: PrintCommand,
     },
     Info {
-        sequence_id: usize,
+        sequence_id: String,
+        #[serde(flatten)]
         command: InfoCommand,
     },
+    Pushing {
+        sequence_id: String,
+        #[serde(flatten)]
+        command: PushingCommand,
+    },
 }
 
 impl Command {
     pub fn system(sequence_id: usize, command: SystemCommand) -> Self {
         Self::System {
-            sequence_id,
+            sequence_id: sequence_id.to_string(),
             command,
         }
     }
 
     pub fn print(sequence_id: usize, command: PrintCommand) -> Self {
         Self::Print {
-            sequence_id,
+            sequence_id: sequence_id.to_string(),
             command,
         }
     }
 
     pub fn info(sequence_id: usize, command: InfoCommand) -> Self {
         Self::Info {
-            sequence_id,
+            sequence_id: sequence_id.to_string(),
+            command,
+        }
+    }
+
+    pub fn pushing(sequence_id: usize, command: PushingCommand) -> Self {
+        Self::Pushing {
+            sequence_id: sequence_id.to_string(),
             command,
         }
     }
 }
 
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
+#[serde(tag = "command", rename_all = "snake_case")]
 pub enum SystemCommand {}
 
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
+#[serde(tag = "command", rename_all = "snake_case")]
 pub enum PrintCommand {
     Pause,
     Resume,
     Stop,
     PushStatus,
+    /// Set print speed level (`1`-`4`)
+    PrintSpeed {
+        param: String,
+    },
+    /// Start printing a project file from printer storage
+    ProjectFile(Box<ProjectFile>),
+    /// Execute raw G-code, lines separated by `\n`
+    GcodeLine {
+        param: String,
+    },
+    /// Skip objects in the current print, by sliced object `identify_id`
+    SkipObjects {
+        obj_list: Vec<u64>,
+    },
+    /// Run printer calibrations, `option` is a bitmask of `CALIBRATE_*` flags
+    Calibration {
+        option: u32,
+    },
 }
 
+/// Micro lidar calibration
+pub const CALIBRATE_LIDAR: u32 = 1 << 0;
+/// Auto bed levelling
+pub const CALIBRATE_BED_LEVELING: u32 = 1 << 1;
+/// Vibration compensation
+pub const CALIBRATE_VIBRATION: u32 = 1 << 2;
+/// Motor noise cancellation
+pub const CALIBRATE_MOTOR_NOISE: u32 = 1 << 3;
+
+/// `project_file` request parameters
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ProjectFile {
+    /// Plate G-code within the project, e.g. `Metadata/plate_1.gcode`
+    pub param: String,
+    /// Project file location, e.g. `file:///sdcard/model.gcode.3mf`
+    pub url: String,
+    pub subtask_name: String,
+    #[serde(default)]
+    pub md5: String,
+    pub project_id: String,
+    pub profile_id: String,
+    pub task_id: String,
+    pub subtask_id: String,
+    pub bed_type: BedType,
+    pub bed_leveling: bool,
+    pub flow_cali: bool,
+    pub vibration_cali: bool,
+    pub layer_inspect: bool,
+    pub timelapse: bool,
+    pub use_ams: bool,
+    /// Tray for each sliced filament, `ams_id * 4 + tray_id`, 254 for external or -1 for unmapped
+    #[serde(default)]
+    pub ams_mapping: Vec<i32>,
+}
+
+/// Build plate types
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize, clap::ValueEnum)]
 #[serde(rename_all = "snake_case")]
+pub enum BedType {
+    /// Use the plate type from the sliced file
+    #[default]
+    Auto,
+    CoolPlate,
+    #[serde(rename = "eng_plate")]
+    #[clap(name = "eng-plate")]
+    EngineeringPlate,
+    #[serde(rename = "hot_plate")]
+    #[clap(name = "hot-plate")]
+    HighTempPlate,
+    TexturedPlate,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
 pub enum InfoCommand {
     GetVersion,
 }
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum PushingCommand {
+    /// Request a full status report
+    Pushall,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    #[test]
+    fn encode_commands() {
+        let tests = &[
+            (
+                Command::print(1, PrintCommand::Pause),
+                json!({"print": {"sequence_id": "1", "command": "pause"}}),
+            ),
+            (
+                Command::print(
+                    2,
+                    PrintCommand::GcodeLine {
+                        param: "M104 S220\n".to_string(),
+                    },
+                ),
+                json!({"print": {"sequence_id": "2", "command": "gcode_line", "param": "M104 S220\n"}}),
+            ),
+            (
+                Command::print(
+                    4,
+                    PrintCommand::SkipObjects {
+                        obj_list: vec![139, 183],
+                    },
+                ),
+                json!({"print": {"sequence_id": "4", "command": "skip_objects", "obj_list": [139, 183]}}),
+            ),
+            (
+                Command::print(
+                    5,
+                    PrintCommand::Calibration {
+                        option: CALIBRATE_BED_LEVELING,
+                    },
+                ),
+                json!({"print": {"sequence_id": "5", "command": "calibration", "option": 2}}),
+            ),
+            (
+                Command::pushing(3, PushingCommand::Pushall),
+                json!({"pushing": {"sequence_id": "3", "command": "pushall"}}),
+            ),
+        ];
+
+        for (c, v) in tests {
+            assert_eq!(&serde_json::to_value(c).unwrap(), v);
+        }
+    }
+}
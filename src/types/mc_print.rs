@@ -0,0 +1,632 @@
+//! `mc_print` / `push_info` debug message parsing
+//!
+//! Messages are prefixed with `[MODULE]` or `[MODULE][SUB]` tags, e.g. `[BMC] X231.0 Y236.0,z_c=0.507,z_d=0.094`,
+//! and parsed by an [McParser] with line parsers registered for each prefix.
+
+use std::{collections::HashMap, str::FromStr};
+
+use regex::{Captures, Regex};
+
+/// Line parser, see [McParser::register]
+pub type LineParser = Box<dyn Fn(&McLine) -> Result<McPrintValue, ParseError> + Send + Sync>;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum McPrintValue {
+    /// AMS feed status, e.g. `[AMS][Period]:(AMS0-S255)cmd_en=1;act_en=1;sta=0;sw=1-1-1-0;c_len=0.000m,cnt=0`
+    AmsFeed {
+        ams: u8,
+        /// Active tray, 255 for none
+        tray: u8,
+        cmd_en: bool,
+        act_en: bool,
+        sta: u8,
+        /// Filament switch states
+        sw: Vec<u8>,
+        /// Fed filament length (m)
+        c_len: f32,
+        cnt: u32,
+    },
+    /// AMS motor telemetry, e.g. `[AMS][Period]:bldc_i=-0.00,u=0.00,spd=0.00;dw_spd=0.00;bdc_i=-0.00,u=0.00,spd=-0.00`
+    AmsMotor {
+        /// Feed (BLDC) motor current
+        bldc_i: f32,
+        /// Feed (BLDC) motor voltage
+        bldc_u: f32,
+        /// Feed (BLDC) motor speed
+        bldc_spd: f32,
+        dw_spd: f32,
+        /// Tray (BDC) motor current
+        bdc_i: f32,
+        /// Tray (BDC) motor voltage
+        bdc_u: f32,
+        /// Tray (BDC) motor speed
+        bdc_spd: f32,
+    },
+    /// Other AMS periodic message
+    AmsPeriod,
+    /// Connected AMS units, e.g. `[AMS][TASK]ams num:1,ams_exist:0x1,tray_now: 255`
+    AmsUnits {
+        num: u8,
+        /// Connected unit bitmask
+        ams_exist: u32,
+        /// Loaded tray, `ams_id * 4 + tray_id` or 255 for none
+        tray_now: u8,
+    },
+    /// AMS tray bitmasks, e.g. `[AMS][TASK]tray_exist:0x7;tray_read_done:0x7,vailed:0x7,reading:0x0`
+    AmsTrays {
+        tray_exist: u32,
+        tray_read_done: u32,
+        valid: u32,
+        reading: u32,
+    },
+    /// AMS environment, e.g. `[AMS][TASK]ams0 temp:27.4;humidity:27%;humidity_idx:3`
+    AmsEnv {
+        ams: u8,
+        temp: f32,
+        /// Relative humidity (%)
+        humidity: f32,
+        /// Humidity index (1-5, lower is wetter)
+        humidity_idx: u8,
+    },
+    /// AMS unit state, e.g. `[AMS][TASK]ams0 en=1,mode=0,sta=0`
+    AmsState {
+        ams: u8,
+        en: bool,
+        mode: u8,
+        sta: u8,
+    },
+    /// Other AMS task message
+    AmsTask,
+    /// Other bed levelling message
+    Bmc,
+    /// Bed levelling measurement
+    BmcMeas { x: f32, y: f32, z_c: f32, z_d: f32 },
+    /// Bed levelling probe, e.g. `[BMC] z_t_cnt=119,p=0.373,rr=7.440,d=0.094,pos=234.7,272.7`
+    BmcProbe {
+        /// Probe touch count
+        z_t_cnt: u32,
+        p: f32,
+        rr: f32,
+        d: f32,
+        x: f32,
+        y: f32,
+    },
+    /// Bed levelling averages, e.g. `[BMC] avr_rr=7.688645,avr_d_rr=0.501`
+    BmcAverage { avr_rr: f32, avr_d_rr: f32 },
+    /// Change from the previous measurement at a point, e.g. `[BMC] PX231.0 Y236.0,prev_z_c_diff=      -0.286      `
+    BmcPrevDiff { x: f32, y: f32, prev_z_c_diff: f32 },
+    /// Message parsed by a registered [McParser] for a module not known to this crate
+    Custom {
+        prefix: String,
+        fields: Vec<(String, String)>,
+    },
+    /// Message with no registered parser
+    Unknown(String),
+}
+
+impl McPrintValue {
+    pub fn is_bmc_meas(&self) -> bool {
+        matches!(self, Self::BmcMeas { .. })
+    }
+}
+
+/// Message parse error
+#[derive(Clone, PartialEq, Debug, thiserror::Error, displaydoc::Display)]
+/// {kind} at position {position}
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    /// Byte offset within the message
+    pub position: usize,
+}
+
+/// Message parse error kinds
+#[derive(Clone, PartialEq, Debug, displaydoc::Display)]
+pub enum ParseErrorKind {
+    /// missing field `{0}`
+    MissingField(String),
+    /// invalid value for `{0}`
+    InvalidValue(String),
+    /// {0}
+    Other(String),
+}
+
+/// Message being parsed, split into `[MODULE][SUB]` prefix and body
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct McLine<'a> {
+    /// Full message
+    pub line: &'a str,
+    /// Message prefix, e.g. `[AMS][TASK]`
+    pub prefix: &'a str,
+    /// Message body following the prefix and any `:` separator
+    pub body: &'a str,
+}
+
+impl<'a> McLine<'a> {
+    /// Split a message into prefix and body
+    pub fn new(line: &'a str) -> Self {
+        let mut end = 0;
+        while line[end..].starts_with('[') {
+            match line[end..].find(']') {
+                Some(i) => end += i + 1,
+                None => break,
+            }
+        }
+
+        Self {
+            line,
+            prefix: &line[..end],
+            body: line[end..].trim_start_matches(':'),
+        }
+    }
+
+    /// Byte offset of a sub-slice of this message
+    pub fn position(&self, sub: &str) -> usize {
+        (sub.as_ptr() as usize)
+            .checked_sub(self.line.as_ptr() as usize)
+            .filter(|p| *p <= self.line.len())
+            .unwrap_or(0)
+    }
+
+    /// Build an error located at a sub-slice of this message
+    pub fn error(&self, at: &str, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            kind,
+            position: self.position(at),
+        }
+    }
+
+    /// Parse a named value from a sub-slice of this message
+    pub fn parse<T: FromStr>(&self, name: &str, value: &str) -> Result<T, ParseError> {
+        value
+            .trim()
+            .parse()
+            .map_err(|_| self.error(value, ParseErrorKind::InvalidValue(name.to_string())))
+    }
+
+    /// Split the body into `key=value` / `key:value` pairs separated by `;` or `,`
+    pub fn fields(&self) -> Vec<(&'a str, &'a str)> {
+        fields(self.body)
+    }
+}
+
+/// Fields of a message body, for error reporting on missing or invalid values
+struct Fields<'a, 'b> {
+    line: &'b McLine<'a>,
+    at: &'a str,
+    fields: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a, 'b> Fields<'a, 'b> {
+    fn new(line: &'b McLine<'a>, s: &'a str) -> Self {
+        Self {
+            line,
+            at: s,
+            fields: fields(s),
+        }
+    }
+
+    fn get(&self, k: &str) -> Option<&'a str> {
+        self.fields.iter().find(|(n, _)| *n == k).map(|(_, v)| *v)
+    }
+
+    fn value(&self, k: &str) -> Result<&'a str, ParseError> {
+        self.get(k).ok_or_else(|| {
+            self.line
+                .error(self.at, ParseErrorKind::MissingField(k.to_string()))
+        })
+    }
+
+    fn parse<T: FromStr>(&self, k: &str) -> Result<T, ParseError> {
+        self.line.parse(k, self.value(k)?)
+    }
+
+    /// Parse a decimal or `0x` prefixed hex value
+    fn int(&self, k: &str) -> Result<u32, ParseError> {
+        let v = self.value(k)?;
+        let r = match v.strip_prefix("0x") {
+            Some(h) => u32::from_str_radix(h, 16).ok(),
+            None => v.parse().ok(),
+        };
+
+        r.ok_or_else(|| {
+            self.line
+                .error(v, ParseErrorKind::InvalidValue(k.to_string()))
+        })
+    }
+
+    fn flag(&self, k: &str) -> Result<bool, ParseError> {
+        Ok(self.parse::<u8>(k)? != 0)
+    }
+}
+
+/// Split `key=value` / `key:value` pairs separated by `;` or `,`
+fn fields(s: &str) -> Vec<(&str, &str)> {
+    s.split([';', ','])
+        .filter_map(|f| f.split_once(['=', ':']))
+        .map(|(k, v)| (k.trim(), v.trim()))
+        .collect()
+}
+
+/// Registry of line parsers keyed on message prefix
+pub struct McParser {
+    parsers: HashMap<String, LineParser>,
+}
+
+impl Default for McParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl McParser {
+    /// Create a parser with the built-in `[AMS]` and `[BMC]` line parsers
+    pub fn new() -> Self {
+        let mut p = Self::empty();
+
+        p.register("[AMS][Period]", parse_ams_period);
+        p.register("[AMS][TASK]", parse_ams_task);
+        p.register("[BMC]", parse_bmc);
+
+        p
+    }
+
+    /// Create a parser with no registered line parsers
+    pub fn empty() -> Self {
+        Self {
+            parsers: HashMap::new(),
+        }
+    }
+
+    /// Register a line parser for a `[MODULE]` or `[MODULE][SUB]` prefix, replacing any existing parser
+    ///
+    /// `[MODULE][SUB]` parsers take precedence over `[MODULE]` parsers.
+    pub fn register(
+        &mut self,
+        prefix: &str,
+        parser: impl Fn(&McLine) -> Result<McPrintValue, ParseError> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.parsers.insert(prefix.to_string(), Box::new(parser));
+        self
+    }
+
+    /// Parse a message, returning [McPrintValue::Unknown] where no parser is registered
+    pub fn parse(&self, s: &str) -> Result<McPrintValue, ParseError> {
+        let s = s.trim_start_matches('\"').trim_end_matches('\"');
+        let line = McLine::new(s);
+
+        // Match the full prefix, then the module alone
+        let module = line.prefix.find(']').map(|i| &line.prefix[..i + 1]);
+        let parser = self
+            .parsers
+            .get(line.prefix)
+            .or_else(|| module.and_then(|m| self.parsers.get(m)));
+
+        match parser {
+            Some(p) => p(&line),
+            None => Ok(McPrintValue::Unknown(s.to_string())),
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref PARSER: McParser = McParser::new();
+
+    static ref BMC_MEAS: Regex = Regex::new("X([0-9.]+) Y([0-9.]+),z_c=[ ]+(-*[0-9.]+)[ ]*,z_d=(-*[0-9.]+)").unwrap();
+
+    static ref BMC_PROBE: Regex = Regex::new("z_t_cnt=([0-9]+),p=(-*[0-9.]+),rr=(-*[0-9.]+),d=(-*[0-9.]+),pos=(-*[0-9.]+),(-*[0-9.]+)").unwrap();
+
+    static ref BMC_PREV: Regex = Regex::new("PX([0-9.]+) Y([0-9.]+),prev_z_c_diff=[ ]*(-*[0-9.]+)").unwrap();
+
+    static ref VAL_PAIR: Regex = Regex::new("([a-zA-Z_]+)=[ ]*(-*[0-9.]+)").unwrap();
+}
+
+impl FromStr for McPrintValue {
+    type Err = ParseError;
+
+    /// Parse a message with the built-in line parsers
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PARSER.parse(s)
+    }
+}
+
+/// Parse `[BMC]` messages
+fn parse_bmc(l: &McLine) -> Result<McPrintValue, ParseError> {
+    let cap = |c: &Captures, i: usize, name: &str| l.parse::<f32>(name, &c[i]);
+
+    if let Some(c) = BMC_MEAS.captures(l.body) {
+        return Ok(McPrintValue::BmcMeas {
+            x: cap(&c, 1, "x")?,
+            y: cap(&c, 2, "y")?,
+            z_c: cap(&c, 3, "z_c")?,
+            z_d: cap(&c, 4, "z_d")?,
+        });
+    }
+
+    if let Some(c) = BMC_PROBE.captures(l.body) {
+        return Ok(McPrintValue::BmcProbe {
+            z_t_cnt: l.parse("z_t_cnt", &c[1])?,
+            p: cap(&c, 2, "p")?,
+            rr: cap(&c, 3, "rr")?,
+            d: cap(&c, 4, "d")?,
+            x: cap(&c, 5, "pos")?,
+            y: cap(&c, 6, "pos")?,
+        });
+    }
+
+    if let Some(c) = BMC_PREV.captures(l.body) {
+        return Ok(McPrintValue::BmcPrevDiff {
+            x: cap(&c, 1, "x")?,
+            y: cap(&c, 2, "y")?,
+            prev_z_c_diff: cap(&c, 3, "prev_z_c_diff")?,
+        });
+    }
+
+    let pairs: HashMap<_, _> = VAL_PAIR
+        .captures_iter(l.body)
+        .filter_map(|c| Some((c.get(1)?.as_str(), c.get(2)?.as_str())))
+        .collect();
+
+    if let (Some(avr_rr), Some(avr_d_rr)) = (pairs.get("avr_rr"), pairs.get("avr_d_rr")) {
+        return Ok(McPrintValue::BmcAverage {
+            avr_rr: l.parse("avr_rr", avr_rr)?,
+            avr_d_rr: l.parse("avr_d_rr", avr_d_rr)?,
+        });
+    }
+
+    Ok(McPrintValue::Bmc)
+}
+
+/// Parse `[AMS][Period]` messages
+fn parse_ams_period(l: &McLine) -> Result<McPrintValue, ParseError> {
+    let s = l.body;
+
+    // Feed status, `(AMS0-S255)cmd_en=1;...`
+    if let Some(unit) = s.strip_prefix("(AMS") {
+        let (unit, rest) = unit
+            .split_once(')')
+            .ok_or_else(|| l.error(unit, ParseErrorKind::Other("unterminated unit".to_string())))?;
+        let (ams, tray) = unit
+            .split_once("-S")
+            .ok_or_else(|| l.error(unit, ParseErrorKind::InvalidValue("unit".to_string())))?;
+        let f = Fields::new(l, rest);
+
+        let sw = f.value("sw")?;
+        let c_len = f.value("c_len")?;
+
+        return Ok(McPrintValue::AmsFeed {
+            ams: l.parse("ams", ams)?,
+            tray: l.parse("tray", tray)?,
+            cmd_en: f.flag("cmd_en")?,
+            act_en: f.flag("act_en")?,
+            sta: f.parse("sta")?,
+            sw: sw
+                .split('-')
+                .map(|v| l.parse("sw", v))
+                .collect::<Result<_, _>>()?,
+            c_len: l.parse("c_len", c_len.trim_end_matches('m'))?,
+            cnt: f.parse("cnt")?,
+        });
+    }
+
+    // Motor telemetry, `u` and `spd` follow the motor current field
+    if s.starts_with("bldc_i") {
+        let mut v = [None; 7];
+        let mut motor = "";
+
+        for (k, val) in fields(s) {
+            let i = match (motor, k) {
+                (_, "bldc_i") => 0,
+                ("bldc", "u") => 1,
+                ("bldc", "spd") => 2,
+                (_, "dw_spd") => 3,
+                (_, "bdc_i") => 4,
+                ("bdc", "u") => 5,
+                ("bdc", "spd") => 6,
+                _ => continue,
+            };
+            if let Some(m) = k.strip_suffix("_i") {
+                motor = m;
+            }
+            v[i] = Some(l.parse::<f32>(k, val)?);
+        }
+
+        const NAMES: [&str; 7] = [
+            "bldc_i", "bldc_u", "bldc_spd", "dw_spd", "bdc_i", "bdc_u", "bdc_spd",
+        ];
+        let get = |i: usize| {
+            v[i].ok_or_else(|| l.error(s, ParseErrorKind::MissingField(NAMES[i].to_string())))
+        };
+
+        return Ok(McPrintValue::AmsMotor {
+            bldc_i: get(0)?,
+            bldc_u: get(1)?,
+            bldc_spd: get(2)?,
+            dw_spd: get(3)?,
+            bdc_i: get(4)?,
+            bdc_u: get(5)?,
+            bdc_spd: get(6)?,
+        });
+    }
+
+    Ok(McPrintValue::AmsPeriod)
+}
+
+/// Parse `[AMS][TASK]` messages
+fn parse_ams_task(l: &McLine) -> Result<McPrintValue, ParseError> {
+    let f = Fields::new(l, l.body);
+
+    if f.get("ams num").is_some() {
+        return Ok(McPrintValue::AmsUnits {
+            num: f.parse("ams num")?,
+            ams_exist: f.int("ams_exist")?,
+            tray_now: f.parse("tray_now")?,
+        });
+    }
+
+    if f.get("tray_exist").is_some() {
+        return Ok(McPrintValue::AmsTrays {
+            tray_exist: f.int("tray_exist")?,
+            tray_read_done: f.int("tray_read_done")?,
+            valid: f.int("vailed")?,
+            reading: f.int("reading")?,
+        });
+    }
+
+    // Per-unit messages, `ams0 temp:27.4;...`
+    let Some((ams, rest)) = l.body.strip_prefix("ams").and_then(|s| s.split_once(' ')) else {
+        return Ok(McPrintValue::AmsTask);
+    };
+    let f = Fields::new(l, rest);
+
+    if f.get("temp").is_some() {
+        let humidity = f.value("humidity")?;
+
+        return Ok(McPrintValue::AmsEnv {
+            ams: l.parse("ams", ams)?,
+            temp: f.parse("temp")?,
+            humidity: l.parse("humidity", humidity.trim_end_matches('%'))?,
+            humidity_idx: f.parse("humidity_idx")?,
+        });
+    }
+
+    if f.get("en").is_some() {
+        return Ok(McPrintValue::AmsState {
+            ams: l.parse("ams", ams)?,
+            en: f.flag("en")?,
+            mode: f.parse("mode")?,
+            sta: f.parse("sta")?,
+        });
+    }
+
+    Ok(McPrintValue::AmsTask)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_mc_params() {
+        let tests = &[
+            (
+                "[AMS][Period]:(AMS0-S255)cmd_en=1;act_en=1;sta=0;sw=1-1-1-0;c_len=0.000m,cnt=0",
+                McPrintValue::AmsFeed{ ams: 0, tray: 255, cmd_en: true, act_en: true, sta: 0, sw: vec![1, 1, 1, 0], c_len: 0.0, cnt: 0 },
+            ),(
+                "[AMS][TASK]ams num:1,ams_exist:0x1,tray_now: 255",
+                McPrintValue::AmsUnits{ num: 1, ams_exist: 0x1, tray_now: 255 },
+            ),(
+                "[AMS][TASK]tray_exist:0x7;tray_read_done:0x7,vailed:0x7,reading:0x0",
+                McPrintValue::AmsTrays{ tray_exist: 0x7, tray_read_done: 0x7, valid: 0x7, reading: 0x0 },
+            ),(
+                "[AMS][TASK]ams0 temp:27.4;humidity:27%;humidity_idx:3",
+                McPrintValue::AmsEnv{ ams: 0, temp: 27.4, humidity: 27.0, humidity_idx: 3 },
+            ),(
+                "[AMS][TASK]ams0 en=1,mode=0,sta=0",
+                McPrintValue::AmsState{ ams: 0, en: true, mode: 0, sta: 0 },
+            ),(
+                "[AMS][Period]:(AMS0-S255)cmd_en=1;act_en=1;sta=0;sw=1-1-1-0;c_len=0.000m,cnt=0",
+                McPrintValue::AmsFeed{ ams: 0, tray: 255, cmd_en: true, act_en: true, sta: 0, sw: vec![1, 1, 1, 0], c_len: 0.0, cnt: 0 },
+            ),(
+                "[AMS][Period]:(AMS1-S2)cmd_en=1;act_en=0;sta=3;sw=0-1-1-0;c_len=1.250m,cnt=17",
+                McPrintValue::AmsFeed{ ams: 1, tray: 2, cmd_en: true, act_en: false, sta: 3, sw: vec![0, 1, 1, 0], c_len: 1.25, cnt: 17 },
+            ),(
+                "[AMS][Period]:bldc_i=-0.00,u=0.00,spd=0.00;dw_spd=0.00;bdc_i=-0.00,u=0.00,spd=-0.00",
+                McPrintValue::AmsMotor{ bldc_i: 0.0, bldc_u: 0.0, bldc_spd: 0.0, dw_spd: 0.0, bdc_i: 0.0, bdc_u: 0.0, bdc_spd: 0.0 },
+            ),(
+                "[AMS][Period]:bldc_i=0.52,u=11.80,spd=402.10;dw_spd=12.50;bdc_i=-0.12,u=-3.00,spd=-7.25",
+                McPrintValue::AmsMotor{ bldc_i: 0.52, bldc_u: 11.8, bldc_spd: 402.1, dw_spd: 12.5, bdc_i: -0.12, bdc_u: -3.0, bdc_spd: -7.25 },
+            ),(
+                "[AMS][TASK]unrecognised message",
+                McPrintValue::AmsTask,
+            ),(
+                "[BMC] z_t_cnt=119,p=0.373,rr=7.440,d=0.094,pos=234.7,272.7",
+                McPrintValue::BmcProbe{ z_t_cnt: 119, p: 0.373, rr: 7.44, d: 0.094, x: 234.7, y: 272.7 },
+            ),(
+                "[BMC] avr_rr=7.688645,avr_d_rr=0.501",
+                McPrintValue::BmcAverage{ avr_rr: 7.688645, avr_d_rr: 0.501 },
+            ),(
+                "[BMC] X231.0 Y236.0,z_c=      0.507      ,z_d=0.094",
+                McPrintValue::BmcMeas{ x: 231.0, y: 236.0, z_c: 0.507, z_d: 0.094},
+            ),(
+                "[BMC] PX231.0 Y236.0,prev_z_c_diff=      -0.286      ",
+                McPrintValue::BmcPrevDiff{ x: 231.0, y: 236.0, prev_z_c_diff: -0.286 },
+            ),(
+                "[BMC] unrecognised message",
+                McPrintValue::Bmc,
+            )
+        ];
+
+        for (s, t) in tests {
+            println!("Parsing: {s}, Expected: {t:?}");
+
+            let t1 = McPrintValue::from_str(s).unwrap();
+            assert_eq!(&t1, t);
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        let tests = &[
+            (
+                "[BMC] X231.0.5 Y236.0,z_c=      0.507      ,z_d=0.094",
+                ParseError {
+                    kind: ParseErrorKind::InvalidValue("x".to_string()),
+                    position: 7,
+                },
+            ),
+            (
+                "[AMS][TASK]ams0 temp:27.4;humidity:27%",
+                ParseError {
+                    kind: ParseErrorKind::MissingField("humidity_idx".to_string()),
+                    position: 16,
+                },
+            ),
+            (
+                "[AMS][TASK]tray_exist:0x7;tray_read_done:0xZ,vailed:0x7,reading:0x0",
+                ParseError {
+                    kind: ParseErrorKind::InvalidValue("tray_read_done".to_string()),
+                    position: 41,
+                },
+            ),
+        ];
+
+        for (s, e) in tests {
+            assert_eq!(&McPrintValue::from_str(s).unwrap_err(), e, "{s}");
+        }
+    }
+
+    #[test]
+    fn custom_parser() {
+        let mut p = McParser::new();
+        p.register("[XCAM]", |l| {
+            Ok(McPrintValue::Custom {
+                prefix: l.prefix.to_string(),
+                fields: l
+                    .fields()
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            })
+        });
+
+        assert_eq!(
+            p.parse("[XCAM][Detect]:score=0.92,cls=3").unwrap(),
+            McPrintValue::Custom {
+                prefix: "[XCAM][Detect]".to_string(),
+                fields: vec![
+                    ("score".to_string(), "0.92".to_string()),
+                    ("cls".to_string(), "3".to_string())
+                ],
+            }
+        );
+
+        assert_eq!(
+            McParser::empty().parse("[BMC] avr_rr=7.688645,avr_d_rr=0.501"),
+            Ok(McPrintValue::Unknown(
+                "[BMC] avr_rr=7.688645,avr_d_rr=0.501".to_string()
+            ))
+        );
+    }
+}
@@ -1,15 +1,19 @@
 use serde::{Deserialize, Serialize};
 
-mod command;
+pub mod command;
 pub use command::*;
 
 mod report;
 pub use report::*;
 
+mod mc_print;
+pub use mc_print::*;
+
 mod errors;
 pub use errors::ERRORS;
 
-#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+/// Printer stage, reported as `stg_cur`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Action {
     /// Printing
@@ -59,3 +63,105 @@ pub enum Action {
     /// Idle
     Idle = 255,
 }
+
+impl TryFrom<i32> for Action {
+    type Error = String;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Printing),
+            1 => Ok(Self::Abl),
+            2 => Ok(Self::HeatbedPreheat),
+            3 => Ok(Self::SweepingXyMechMode),
+            4 => Ok(Self::ChangingFilament),
+            5 => Ok(Self::M400Pause),
+            6 => Ok(Self::FilamentRunoutPause),
+            7 => Ok(Self::HeatingHotend),
+            8 => Ok(Self::CalibratingExtrusion),
+            9 => Ok(Self::ScanningBedSurface),
+            10 => Ok(Self::InspectingFirstLayer),
+            11 => Ok(Self::IdentifyingBuildPlateType),
+            12 => Ok(Self::CalibratingMicroLidar),
+            13 => Ok(Self::HomingToolhead),
+            14 => Ok(Self::CleaningNozzleTip),
+            15 => Ok(Self::CheckingExtruderTemperature),
+            16 => Ok(Self::UserPause),
+            17 => Ok(Self::FrontCoverPause),
+            18 => Ok(Self::CalibratingMicroLidar2),
+            19 => Ok(Self::CalibratingExtrusionFlow),
+            20 => Ok(Self::NozzleTempMalfunction),
+            21 => Ok(Self::GearBedTempMalfunction),
+            255 => Ok(Self::Idle),
+            _ => Err(format!("unknown stage: {value}")),
+        }
+    }
+}
+
+/// Print speed profile, reported as `spd_lvl`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum SpeedProfile {
+    /// Silent (50%)
+    Silent = 1,
+    /// Standard (100%)
+    Standard = 2,
+    /// Sport (124%)
+    Sport = 3,
+    /// Ludicrous (166%)
+    Ludicrous = 4,
+}
+
+impl SpeedProfile {
+    /// Nominal speed magnitude (%) for the profile
+    pub fn magnitude(&self) -> usize {
+        match self {
+            Self::Silent => 50,
+            Self::Standard => 100,
+            Self::Sport => 124,
+            Self::Ludicrous => 166,
+        }
+    }
+}
+
+impl TryFrom<u8> for SpeedProfile {
+    type Error = String;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Self::Silent),
+            2 => Ok(Self::Standard),
+            3 => Ok(Self::Sport),
+            4 => Ok(Self::Ludicrous),
+            _ => Err(format!("invalid speed level: {value}")),
+        }
+    }
+}
+
+impl From<SpeedProfile> for u8 {
+    fn from(value: SpeedProfile) -> Self {
+        value as u8
+    }
+}
+
+/// Print job state, reported as `gcode_state`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize, displaydoc::Display)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum GcodeState {
+    /// Idle
+    Idle,
+    /// Preparing print
+    Prepare,
+    /// Slicing
+    Slicing,
+    /// Running
+    Running,
+    /// Paused
+    Pause,
+    /// Finished
+    Finish,
+    /// Failed
+    Failed,
+    /// Unknown state
+    #[serde(other)]
+    Unknown,
+}
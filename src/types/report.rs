@@ -1,9 +1,10 @@
-use std::{str::FromStr, default};
+use std::default;
 
-use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use super::{Action, GcodeState, SpeedProfile};
+
 // TODO: rework everything to do with this
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -31,29 +32,230 @@ pub enum Report {
 #[serde(rename_all = "snake_case")]
 pub struct PrintValue {
     #[serde(default, skip_serializing_if = "is_default")]
-    ams: Option<Ams>,
+    pub ams: Option<Ams>,
+    /// External spool holder
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub vt_tray: Option<Tray>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub gcode_state: Option<GcodeState>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub upgrade_state: Option<UpgradeState>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub module: Vec<ModuleInfo>,
+    /// Part cooling fan speed (0-15)
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub cooling_fan_speed: Option<String>,
+    /// Aux fan speed (0-15)
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub big_fan1_speed: Option<String>,
+    /// Chamber fan speed (0-15)
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub big_fan2_speed: Option<String>,
+    /// Packed fan PWM values (0-255), part | aux << 8 | chamber << 16
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub fan_gear: Option<isize>,
+    /// Print speed profile, see [PrintValue::speed_profile]
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub spd_lvl: Option<u8>,
+    /// Print speed magnitude (%)
     #[serde(default, skip_serializing_if = "is_default")]
-    upgrade_state: Option<UpgradeState>,
+    pub spd_mag: Option<usize>,
+    /// Objects skipped in the current print, by sliced object `identify_id`
     #[serde(default, skip_serializing_if = "is_default")]
-    module: Vec<ModuleInfo>,
+    pub s_obj: Option<Vec<u64>>,
     #[serde(default, skip_serializing_if = "is_default")]
-    cooling_fan_speed: Option<String>,
+    pub nozzle_temper: Option<f32>,
     #[serde(default, skip_serializing_if = "is_default")]
-    fan_gear: Option<isize>,
+    pub bed_temper: Option<f32>,
+    /// Installed nozzle diameter (mm)
     #[serde(default, skip_serializing_if = "is_default")]
-    nozzle_temper: Option<f32>,
+    pub nozzle_diameter: Option<String>,
+    /// SD card inserted
     #[serde(default, skip_serializing_if = "is_default")]
-    bed_temper: Option<f32>,
+    pub sdcard: Option<bool>,
+    /// Active HMS (health management system) alerts
     #[serde(default, skip_serializing_if = "is_default")]
-    version: usize,
+    pub hms: Option<Vec<Hms>>,
+    /// Camera detection settings
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub xcam: Option<XCam>,
+    /// Current stage, see [Action]
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub stg_cur: Option<i32>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub version: usize,
+}
+
+impl PrintValue {
+    /// Merge a (possibly partial) status report into the current state
+    pub fn update(&mut self, other: &PrintValue) {
+        let mut current = serde_json::to_value(&*self).unwrap_or_default();
+        let other = serde_json::to_value(other).unwrap_or_default();
+
+        merge(&mut current, other);
+
+        if let Ok(v) = serde_json::from_value(current) {
+            *self = v;
+        }
+    }
+
+    /// Decode the current stage, `None` if unknown or not reported
+    pub fn stage(&self) -> Option<Action> {
+        self.stg_cur.and_then(|s| Action::try_from(s).ok())
+    }
+
+    /// Decode the current speed profile, `None` if unknown or not reported
+    pub fn speed_profile(&self) -> Option<SpeedProfile> {
+        self.spd_lvl.and_then(|s| SpeedProfile::try_from(s).ok())
+    }
+
+    /// Fetch information for the currently loaded AMS tray or external spool
+    pub fn active_tray(&self) -> Option<&TrayInfo> {
+        let ams = self.ams.as_ref()?;
+        let tray_now = ams.tray_now.as_ref()?.parse::<usize>().ok()?;
+
+        match tray_now {
+            255 => None,
+            254 => self.vt_tray.as_ref().map(|t| &t.info),
+            n => {
+                let (ams_id, tray_id) = ((n / 4).to_string(), (n % 4).to_string());
+
+                ams.ams
+                    .iter()
+                    .find(|a| a.id == ams_id)?
+                    .tray
+                    .iter()
+                    .find(|t| t.id == tray_id)
+                    .map(|t| &t.info)
+            }
+        }
+    }
+
+    /// Decode fan speeds to percentages
+    ///
+    /// Uses the per-fan speed fields where reported, falling back to `fan_gear`.
+    pub fn fan_speeds(&self) -> FanSpeeds {
+        let speed = |s: &Option<String>| {
+            s.as_ref()
+                .and_then(|s| s.parse::<u32>().ok())
+                .map(|v| (v.min(15) * 100 / 15) as u8)
+        };
+        let gear = |shift: usize| {
+            self.fan_gear
+                .map(|g| (((g >> shift) & 0xFF) as u32 * 100 / 255) as u8)
+        };
+
+        FanSpeeds {
+            part: speed(&self.cooling_fan_speed).or_else(|| gear(0)),
+            aux: speed(&self.big_fan1_speed).or_else(|| gear(8)),
+            chamber: speed(&self.big_fan2_speed).or_else(|| gear(16)),
+        }
+    }
+}
+
+/// Decoded fan speeds (%)
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct FanSpeeds {
+    /// Part cooling fan
+    pub part: Option<u8>,
+    /// Aux (big) fan
+    pub aux: Option<u8>,
+    /// Chamber fan
+    pub chamber: Option<u8>,
+}
+
+/// Recursively merge JSON objects, replacing any non-object values
+fn merge(a: &mut Value, b: Value) {
+    match (a, b) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (k, v) in b {
+                merge(a.entry(k).or_insert(Value::Null), v);
+            }
+        }
+        (a, b) => *a = b,
+    }
 }
 
 #[derive(Clone, PartialEq, Default, Debug, Serialize, Deserialize)]
 pub struct Ams {
-    #[serde(default)]
-    ams: Vec<AmsInfo>,
-    version: usize,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub ams: Vec<AmsInfo>,
+    /// Currently loaded tray, `ams_id * 4 + tray_id`, 254 for the external spool or 255 for none
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub tray_now: Option<String>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub version: usize,
+}
+
+/// HMS alert
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug, Serialize, Deserialize)]
+pub struct Hms {
+    pub attr: u32,
+    pub code: u32,
+}
+
+/// HMS alert severity
+#[derive(Clone, Copy, PartialEq, Eq, Debug, displaydoc::Display)]
+pub enum HmsLevel {
+    /// fatal
+    Fatal,
+    /// serious
+    Serious,
+    /// common
+    Common,
+    /// info
+    Info,
+    /// unknown
+    Unknown,
+}
+
+impl Hms {
+    /// Error code in the format used by [crate::types::ERRORS], e.g. `0300_1000_0002_0001`
+    pub fn error_code(&self) -> String {
+        format!(
+            "{:04X}_{:04X}_{:04X}_{:04X}",
+            self.attr >> 16,
+            self.attr & 0xFFFF,
+            self.code >> 16,
+            self.code & 0xFFFF
+        )
+    }
+
+    /// Alert severity
+    pub fn level(&self) -> HmsLevel {
+        match self.code >> 16 {
+            1 => HmsLevel::Fatal,
+            2 => HmsLevel::Serious,
+            3 => HmsLevel::Common,
+            4 => HmsLevel::Info,
+            _ => HmsLevel::Unknown,
+        }
+    }
+
+    /// Look up the alert description
+    pub fn message(&self) -> Option<&'static str> {
+        let code = self.error_code();
+
+        super::ERRORS
+            .iter()
+            .find(|(c, _)| *c == code)
+            .map(|(_, m)| *m)
+    }
 }
+
+/// Camera detection settings
+#[derive(Clone, PartialEq, Default, Debug, Serialize, Deserialize)]
+pub struct XCam {
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub buildplate_marker_detector: Option<bool>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub first_layer_inspector: Option<bool>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub spaghetti_detector: Option<bool>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub print_halt: Option<bool>,
+}
+
 #[derive(Clone, PartialEq, Default, Debug, Serialize, Deserialize)]
 pub struct UpgradeState {
     dis_state: usize,
@@ -138,80 +340,6 @@ pub enum McPrintCommand {
     PushInfo,
 }
 
-#[derive(Clone, PartialEq, Debug)]
-pub enum McPrintValue {
-    AmsPeriod,
-    AmsTask,
-    Bmc,
-    /// Bed levelling measurement
-    BmcMeas {
-        x: f32,
-        y: f32,
-        z_c: f32,
-        z_d: f32,
-    },
-    Unknown(String),
-}
-
-impl McPrintValue {
-    pub fn is_bmc_meas(&self) -> bool {
-        match self {
-            Self::BmcMeas { .. } => true,
-            _ => false,
-        }
-    }
-}
-
-lazy_static::lazy_static! {
-    static ref BMC_MEAS: Regex = Regex::new("X([0-9.]+) Y([0-9.]+),z_c=[ ]+(-*[0-9.]+)[ ]*,z_d=(-*[0-9.]+)").unwrap();
-
-    static ref VAL_PAIR: Regex = Regex::new("([a-zA-Z_]+)=([0-9.]+)").unwrap();
-}
-
-impl FromStr for McPrintValue {
-    type Err = ();
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let s = s.trim_start_matches('\"').trim_end_matches('\"');
-
-        // TODO: match and parse values
-        if s.starts_with("[AMS][Period]") {
-            return Ok(Self::AmsPeriod);
-        } else if s.starts_with("[AMS][TASK]") {
-            return Ok(Self::AmsTask);
-        } else if s.starts_with("[BMC]") {
-            let s = s.trim_start_matches("[BMC] ");
-
-            if let Some(c) = BMC_MEAS.captures(s) {
-                return Ok(Self::BmcMeas {
-                    x: c.get(1)
-                        .map(|v| f32::from_str(v.as_str()))
-                        .unwrap()
-                        .unwrap(),
-                    y: c.get(2)
-                        .map(|v| f32::from_str(v.as_str()))
-                        .unwrap()
-                        .unwrap(),
-                    z_c: c
-                        .get(3)
-                        .map(|v| f32::from_str(v.as_str()))
-                        .unwrap()
-                        .unwrap(),
-                    z_d: c
-                        .get(4)
-                        .map(|v| f32::from_str(v.as_str()))
-                        .unwrap()
-                        .unwrap(),
-                });
-            } else {
-                return Ok(Self::Bmc);
-            }
-        }
-
-        Ok(Self::Unknown(s.to_string()))
-    }
-}
-
 fn is_default<T: Default + PartialEq>(value: &T) -> bool {
     *value == T::default()
 }
@@ -330,6 +458,7 @@ mod test {
                             },
                         ],
                     }],
+                    tray_now: None,
                     version: 564,
                 }),
                 ..Default::default()
@@ -416,6 +545,147 @@ mod test {
         test_report_serde(raw, report);
     }
 
+    #[test]
+    fn test_report_speed() {
+        let raw = json!({"print":{"command":"push_status","msg":1,"sequence_id":"212","spd_lvl":3,"spd_mag":124}});
+        let report = Report::Print {
+            command: PrintCommand::PushStatus,
+            sequence_id: "212".to_string(),
+            value: PrintValue{
+                spd_lvl: Some(3),
+                spd_mag: Some(124),
+                ..Default::default()
+            },
+        };
+
+        test_report_serde(raw, report);
+
+        // Unknown profiles must not discard the rest of the report
+        let unknown = json!({"print":{"command":"push_status","sequence_id":"213","spd_lvl":7,"bed_temper":55.0}});
+        let Report::Print { value, .. } = serde_json::from_value::<Report>(unknown).unwrap() else {
+            panic!("expected print report");
+        };
+        assert_eq!(value.spd_lvl, Some(7));
+        assert_eq!(value.speed_profile(), None);
+        assert_eq!(value.bed_temper, Some(55.0));
+
+        let known = PrintValue {
+            spd_lvl: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(known.speed_profile(), Some(SpeedProfile::Sport));
+    }
+
+    #[test]
+    fn test_report_gcode_state() {
+        let raw = json!({"print":{"command":"push_status","gcode_state":"RUNNING","msg":1,"sequence_id":"301"}});
+        let report = Report::Print {
+            command: PrintCommand::PushStatus,
+            sequence_id: "301".to_string(),
+            value: PrintValue{
+                gcode_state: Some(GcodeState::Running),
+                ..Default::default()
+            },
+        };
+
+        test_report_serde(raw, report);
+    }
+
+    #[test]
+    fn test_fan_speeds() {
+        let status = PrintValue {
+            cooling_fan_speed: Some("15".to_string()),
+            big_fan1_speed: Some("0".to_string()),
+            fan_gear: Some(0x80_00_FF),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            status.fan_speeds(),
+            FanSpeeds {
+                part: Some(100),
+                aux: Some(0),
+                chamber: Some(50),
+            }
+        );
+    }
+
+    #[test]
+    fn test_report_hms() {
+        let raw = json!({
+            "print": {
+                "command": "push_status",
+                "sequence_id": "12",
+                "sdcard": true,
+                "nozzle_diameter": "0.4",
+                "hms": [{"attr": 50335744, "code": 131073}],
+                "xcam": {"buildplate_marker_detector": true},
+            }
+        });
+
+        let hms = Hms {
+            attr: 0x0300_1000,
+            code: 0x0002_0001,
+        };
+        let report = Report::Print {
+            sequence_id: "12".to_string(),
+            command: PrintCommand::PushStatus,
+            value: PrintValue {
+                sdcard: Some(true),
+                nozzle_diameter: Some("0.4".to_string()),
+                hms: Some(vec![hms]),
+                xcam: Some(XCam {
+                    buildplate_marker_detector: Some(true),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        };
+
+        test_report_serde(raw, report);
+
+        assert_eq!(hms.error_code(), "0300_1000_0002_0001");
+        assert_eq!(hms.level(), HmsLevel::Serious);
+        assert_eq!(
+            hms.message(),
+            Some("The 1st order mechanical resonance mode of X axis is low.")
+        );
+    }
+
+    #[test]
+    fn test_status_update() {
+        let mut status = PrintValue {
+            nozzle_temper: Some(85.0),
+            ams: Some(Ams {
+                ams: vec![AmsInfo {
+                    id: "0".to_string(),
+                    tray: vec![Tray {
+                        id: "2".to_string(),
+                        info: TrayInfo {
+                            tray_type: "PETG".to_string(),
+                            ..Default::default()
+                        },
+                    }],
+                    ..Default::default()
+                }],
+                version: 1,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(status.active_tray(), None);
+
+        let partial: PrintValue = serde_json::from_value(json!({
+            "bed_temper": 60.0,
+            "ams": { "tray_now": "2" },
+        })).unwrap();
+        status.update(&partial);
+
+        assert_eq!(status.nozzle_temper, Some(85.0));
+        assert_eq!(status.bed_temper, Some(60.0));
+        assert_eq!(status.active_tray().map(|t| t.tray_type.as_str()), Some("PETG"));
+    }
+
 
     /// Helper to test report serialisation and deserialisation
     fn test_report_serde(raw: Value, report: Report) {
@@ -429,51 +699,4 @@ mod test {
         let decoded: Report = serde_json::from_value(raw.clone()).expect("failed to decode");
         assert_eq!(decoded, report);
     }
-
-    #[test]
-    fn parse_mc_params() {
-        let tests = &[
-            (
-                "[AMS][Period]:(AMS0-S255)cmd_en=1;act_en=1;sta=0;sw=1-1-1-0;c_len=0.000m,cnt=0",
-                McPrintValue::AmsPeriod,
-            ),(
-                "[AMS][TASK]ams num:1,ams_exist:0x1,tray_now: 255",
-                McPrintValue::AmsTask,
-            ),(
-                "[AMS][TASK]tray_exist:0x7;tray_read_done:0x7,vailed:0x7,reading:0x0",
-                McPrintValue::AmsTask,
-            ),(
-                "[AMS][TASK]ams0 temp:27.4;humidity:27%;humidity_idx:3",
-                McPrintValue::AmsTask,
-            ),(
-                "[AMS][TASK]ams0 en=1,mode=0,sta=0",
-                McPrintValue::AmsTask,
-            ),(
-                "[AMS][Period]:(AMS0-S255)cmd_en=1;act_en=1;sta=0;sw=1-1-1-0;c_len=0.000m,cnt=0",
-                McPrintValue::AmsPeriod,
-            ),(
-                "[AMS][Period]:bldc_i=-0.00,u=0.00,spd=0.00;dw_spd=0.00;bdc_i=-0.00,u=0.00,spd=-0.00",
-                McPrintValue::AmsPeriod,
-            ),(
-                "[BMC] z_t_cnt=119,p=0.373,rr=7.440,d=0.094,pos=234.7,272.7",
-                McPrintValue::Bmc,
-            ),(
-                "[BMC] avr_rr=7.688645,avr_d_rr=0.501",
-                McPrintValue::Bmc,
-            ),(
-                "[BMC] X231.0 Y236.0,z_c=      0.507      ,z_d=0.094",
-                McPrintValue::BmcMeas{ x: 231.0, y: 236.0, z_c: 0.507, z_d: 0.094},
-            ),(
-                "[BMC] PX231.0 Y236.0,prev_z_c_diff=      -0.286      ",
-                McPrintValue::Bmc,
-            )
-        ];
-
-        for (s, t) in tests {
-            println!("Parsing: {s}, Expected: {t:?}");
-
-            let t1 = McPrintValue::from_str(s).unwrap();
-            assert_eq!(&t1, t);
-        }
-    }
 }
@@ -11,7 +11,10 @@ use plotters_iced::{Chart, ChartBuilder, ChartWidget, DrawingBackend};
 use num_traits::float::FloatConst;
 
 use crate::Message;
-use bambu::level::{LevelMap, Point};
+use bambu::level::{Interpolation, LevelMap, Point};
+
+/// Surface grid resolution (points per axis)
+const SURFACE_RESOLUTION: usize = 48;
 
 /// Chart for rendering bed levelling information
 pub struct BedChart {
@@ -89,21 +92,31 @@ impl Chart<Message> for BedChart {
             .draw()
             .unwrap();
 
-        chart
-            .draw_series(
-                SurfaceSeries::xoz(
-                    self.level.xs.iter().map(|v| *v as f64),
-                    self.level.ys.iter().map(|v| *v as f64),
-                    |x, y| {
-                        self.level
-                            .value(x as f32, y as f32)
-                            .map(|p| p.c as f64)
-                            .unwrap_or_default()
-                    },
-                )
-                .style_func(&|&v| (VulcanoHSL::get_color(v * 2.0)).into()),
-            )
-            .unwrap();
+        // Resample the mesh on a regular grid, only drawing cells with measured corners
+        let mesh = self.level.resample(
+            SURFACE_RESOLUTION,
+            SURFACE_RESOLUTION,
+            Interpolation::Bicubic,
+        );
+        let corner = |x: f32, y: f32| mesh.value(x, y).map(|p| (x as f64, p.c as f64, y as f64));
+
+        let cells = mesh
+            .ys
+            .windows(2)
+            .flat_map(|ys| mesh.xs.windows(2).map(move |xs| (xs, ys)))
+            .filter_map(|(xs, ys)| {
+                let c = vec![
+                    corner(xs[0], ys[0])?,
+                    corner(xs[1], ys[0])?,
+                    corner(xs[1], ys[1])?,
+                    corner(xs[0], ys[1])?,
+                ];
+                let v = c.iter().map(|p| p.1).sum::<f64>() / 4.0;
+
+                Some(Polygon::new(c, VulcanoHSL::get_color(v * 2.0)))
+            });
+
+        chart.draw_series(cells).unwrap();
 
         chart
             .configure_series_labels()